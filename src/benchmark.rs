@@ -1,43 +1,123 @@
-use crate::io::RnaSequence;
-use crate::compare::Score;
+use crate::io::{Dataset, RnaSequence};
+use crate::compare::{PairMetrics, Score};
+use crate::elements::{ElementKind, ElementRecovery, SIZE_CAP};
+use crate::folder::{Folder, Registry};
+use crate::profile::{Axis, ErrorProfile};
+use crate::trna::{Arm, CloverleafRecovery};
 use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+// Which part of the experimental structure predictions are scored against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reference {
+    #[default]
+    Full,
+    Nested,
+}
 
-pub struct Benchmark {
-    vienna_avg: f64,
-    min_vienna: (String, f64),
-    max_vienna: (String, f64),
-    std_vienna: f64,
+// Per-sequence score the benchmark ranks and averages, in percent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Metric {
+    #[default]
+    Match,
+    Sensitivity,
+    Ppv,
+    F1,
+    Mcc,
+    Cloverleaf, // 100 when the tRNA cloverleaf is recovered, else 0
+}
 
-    score_distribution_vienna: [i32; 5],
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Metric::Match => "Match Score",
+            Metric::Sensitivity => "Sensitivity",
+            Metric::Ppv => "PPV",
+            Metric::F1 => "F1",
+            Metric::Mcc => "MCC",
+            Metric::Cloverleaf => "Cloverleaf Recovery",
+        };
+        write!(f, "{}", name)
+    }
+}
 
-    top_scores_vienna:    Vec<(String, f64)>,
-    bottom_scores_vienna: Vec<(String, f64)>,
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub reference: Reference,
+    pub metric: Metric,
+    pub slippage: usize, // tolerance of the pair metrics reported next to the exact ones
+    pub helix_threshold: f64, // share of its pairs a predicted helix needs to count as found
+    pub profile_axis: Axis,   // how positions are aligned in the error profile
+    pub top_k: usize,         // candidates per sequence for the best-of-top-k metrics, 1 for none
+}
 
-    nussinov_avg: f64,
-    min_nussinov: (String, f64),
-    max_nussinov: (String, f64),
-    std_nussinov: f64,
+impl Default for Options {
+    fn default() -> Self {
+        Options { reference: Reference::Full, metric: Metric::Match, slippage: 1, helix_threshold: 0.5, profile_axis: Axis::Sprinzl, top_k: 1 }
+    }
+}
 
-    score_distribution_nussinov: [i32; 5],
+// Element kinds reported by the benchmark, with their plural label.
+const REPORTED_ELEMENTS: [(ElementKind, &str); 5] = [
+    (ElementKind::Stem, "Helices"),
+    (ElementKind::Hairpin, "Hairpins"),
+    (ElementKind::Bulge, "Bulges"),
+    (ElementKind::InteriorLoop, "Interior loops"),
+    (ElementKind::Multiloop, "Multiloops"),
+];
+
+// Score and comparisons of one prediction with its reference.
+struct SequenceResult {
+    id: String,
+    score: Option<f64>, // None without a reference to score against
+    pair_metrics: Option<(PairMetrics, PairMetrics)>, // exact, with slippage
+    top_k_metrics: Option<PairMetrics>,
+    element_recovery: Option<ElementRecovery>,
+    cloverleaf_recovery: Option<CloverleafRecovery>,
+    error_profile: Option<ErrorProfile>,
+}
 
-    top_scores_nussinov:    Vec<(String, f64)>,
-    bottom_scores_nussinov: Vec<(String, f64)>, 
+// Results of one algorithm over the whole dataset.
+#[derive(Debug, Clone, Default)]
+pub struct AlgorithmResults {
+    pub parameters: String,
+    pub avg: f64,
+    pub std: f64,
+    pub min: (String, f64),
+    pub max: (String, f64),
+    pub top_scores: Vec<(String, f64)>,
+    pub bottom_scores: Vec<(String, f64)>,
+    pub score_distribution: [i32; 5],
+    pub pair_metrics: PairMetrics,
+    pub slippage_metrics: PairMetrics,
+    pub top_k_metrics: PairMetrics, // of the best of the top k candidates, by F1
+    pub element_recovery: ElementRecovery,
+    pub cloverleaf_recovery: CloverleafRecovery,
+    pub error_profile: ErrorProfile,
+    pub failures: Vec<(String, String)>, // (sequence id, error), left out of the numbers above
+    pub unscored: usize,                  // sequences without a reference for the metric (or a cloverleaf one), left out of avg and std
+}
 
+pub struct Benchmark {
+    metric: Metric,
+    slippage: usize,
+    helix_threshold: f64,
+    top_k: usize,
+    results: BTreeMap<String, AlgorithmResults>,
     quantity: i32,
 }
 
 
 impl Benchmark {
 
-    fn std_dev(avg: f64, score_list: &Vec<(String, f64)>) -> f64 {
+    fn std_dev(avg: f64, score_list: &[(String, f64)]) -> f64 {
         let n = score_list.len();
         if n <= 1 {
             return 0.0;
         }
-        let mut score_sum = 0.0;
-        for i in 0..n {
-            score_sum += (score_list[i].1 - avg).powf(2.0);
-        }
+        let score_sum: f64 = score_list.iter().map(|(_, score)| (score - avg).powf(2.0)).sum();
         (score_sum / ((n - 1) as f64)).sqrt()
     }
 
@@ -81,92 +161,198 @@ impl Benchmark {
         merged
     }
 
-    fn get_distribution(scores_list: &Vec<(String, f64)>) -> [i32; 5] {
+    fn get_distribution(scores_list: &[(String, f64)]) -> [i32; 5] {
         let mut arr: [i32; 5] = [0; 5];
-        for i in 0..scores_list.len() {
-            if scores_list[i].1 >= 0.0 && scores_list[i].1 < 20.0 {
+        for (_, score) in scores_list {
+            if *score < 20.0 {
                 arr[0] += 1;
-            } else if scores_list[i].1 >= 20.0 && scores_list[i].1 < 40.0 {
+            } else if *score < 40.0 {
                 arr[1] += 1;
-            } else if scores_list[i].1 >= 40.0 && scores_list[i].1 < 60.0 {
+            } else if *score < 60.0 {
                 arr[2] += 1;
-            } else if scores_list[i].1 >= 60.0 && scores_list[i].1 < 80.0 {
+            } else if *score < 80.0 {
                 arr[3] += 1;
-            } else if scores_list[i].1 >= 80.0 && scores_list[i].1 <= 100.0 {
+            } else if *score <= 100.0 {
                 arr[4] += 1;
             }
         }
         arr
     }
 
-    fn get_score(li: RnaSequence, algo: &str) -> (String, f64) {
-        let score = Score::new(li, algo).expect("failed to build Score");
-        let score_num: f64 = score.get_score().expect("REASON");
-        let score_id: String = score.get_id().expect("REASON").to_string();
-        (score_id, score_num)
+    // Sequences without usable pair metrics have no score on the pair based
+    // metrics, nor on the match score without any reference, nor on the
+    // cloverleaf one when the reference is not a cloverleaf.
+    fn get_score(li: &RnaSequence, folder: &dyn Folder, options: Options) -> Result<SequenceResult, Box<dyn std::error::Error>> {
+        let score = Score::with_folder(li, folder)?;
+        let pair_metrics = score.get_metrics().ok().copied();
+        let slippage_metrics = score.get_slippage_metrics(options.slippage).ok();
+        let element_recovery = score.get_element_recovery(options.helix_threshold).ok();
+        let cloverleaf_recovery = score.get_cloverleaf_recovery(options.helix_threshold).ok();
+        let error_profile = score.get_error_profile(options.profile_axis).ok();
+        let top_k_metrics = match options.top_k {
+            0 | 1 => None,
+            k => Self::best_of(li, folder, k),
+        };
+        let score_num: Option<f64> = match (options.metric, pair_metrics) {
+            (Metric::Match, _) if li.has_structure() || li.invalid_fold.is_some() => Some(score.get_score()?),
+            (Metric::Match, _) => None,
+            (Metric::Cloverleaf, _) => cloverleaf_recovery.as_ref().map(|r| r.cloverleaf.found as f64 * 100.0),
+            (_, None) => None,
+            (Metric::Sensitivity, Some(m)) => Some(m.sensitivity * 100.0),
+            (Metric::Ppv, Some(m)) => Some(m.ppv * 100.0),
+            (Metric::F1, Some(m)) => Some(m.f1 * 100.0),
+            (Metric::Mcc, Some(m)) => Some(m.mcc * 100.0),
+        };
+        Ok(SequenceResult {
+            id: score.get_id()?.to_string(),
+            score: score_num,
+            pair_metrics: pair_metrics.zip(slippage_metrics),
+            top_k_metrics,
+            element_recovery,
+            cloverleaf_recovery,
+            error_profile,
+        })
     }
 
 
-    fn get_data(seq_list: Vec<RnaSequence>, algo: &str, max: i32) -> (f64, f64, (String, f64), (String, f64), Vec<(String, f64)>, Vec<(String, f64)>, [i32; 5]) {
-        let results: Vec<(String, f64)> = seq_list.par_iter()
-            .map(|seq| Self::get_score(seq.clone(), algo) )
+    // Pair metrics of the candidate closest to the reference, by F1.
+    fn best_of(li: &RnaSequence, folder: &dyn Folder, k: usize) -> Option<PairMetrics> {
+        let reference = li.structure.as_ref()?;
+        folder
+            .candidates(li, k)
+            .ok()?
+            .iter()
+            .map(|candidate| PairMetrics::from_structures(reference, candidate))
+            .reduce(|best, metrics| if metrics.f1 > best.f1 { metrics } else { best })
+    }
+
+    // Sequences the folder fails on are recorded and skipped.
+    fn get_data(seq_list: &[RnaSequence], folder: &dyn Folder, options: Options) -> AlgorithmResults {
+        let outcomes: Vec<Result<SequenceResult, (String, String)>> = seq_list.par_iter()
+            .map(|seq| Self::get_score(seq, folder, options).map_err(|e| (seq.get_id(), e.to_string())))
+            .collect();
+        let mut scored: Vec<SequenceResult> = Vec::with_capacity(outcomes.len());
+        let mut failures = Vec::new();
+        for outcome in outcomes {
+            match outcome {
+                Ok(result) => scored.push(result),
+                Err(failure) => failures.push(failure),
+            }
+        }
+
+        let (exact, slipped): (Vec<PairMetrics>, Vec<PairMetrics>) = scored.iter().filter_map(|s| s.pair_metrics).unzip();
+        let top_k: Vec<PairMetrics> = scored.iter().filter_map(|s| s.top_k_metrics).collect();
+
+        let mut element_recovery = ElementRecovery::default();
+        let mut cloverleaf_recovery = CloverleafRecovery::default();
+        let mut error_profile = ErrorProfile::default();
+        for s in &scored {
+            if let Some(recovery) = &s.element_recovery {
+                element_recovery.merge(recovery);
+            }
+            if let Some(recovery) = &s.cloverleaf_recovery {
+                cloverleaf_recovery.merge(recovery);
+            }
+            if let Some(profile) = &s.error_profile {
+                error_profile.merge(profile);
+            }
+        }
+
+        let unscored = scored.iter().filter(|s| s.score.is_none()).count();
+        let results: Vec<(String, f64)> = scored.into_iter()
+            .filter_map(|s| Some((s.id, s.score?)))
             .collect();
 
         let scores_sum: f64 = results.par_iter()
             .map(|(_, score)| score)
             .sum();
 
-        let avg = scores_sum / (max as f64);
+        // an empty family has nothing to average
+        let avg = if results.is_empty() { 0.0 } else { scores_sum / (results.len() as f64) };
     
         let std = Self::std_dev(avg, &results);
 
         let sorted_scores = Self::merge_sort(results.clone());
 
-        let top_five: Vec<(String, f64)> = sorted_scores.iter().rev().take(5).cloned().collect();
-        let max_score: (String, f64) = if !top_five.is_empty() { top_five[0].clone() } else { (String::new(), 0.0) };
-        let bottom_five: Vec<(String, f64)> = sorted_scores.iter().take(5).cloned().collect();
-        let min_score: (String, f64) = if !bottom_five.is_empty() { bottom_five[0].clone() } else { (String::new(), 0.0) };
+        let top_scores: Vec<(String, f64)> = sorted_scores.iter().rev().take(5).cloned().collect();
+        let bottom_scores: Vec<(String, f64)> = sorted_scores.iter().take(5).cloned().collect();
+
+        AlgorithmResults {
+            parameters: folder.parameters(),
+            avg,
+            std,
+            min: bottom_scores.first().cloned().unwrap_or_default(),
+            max: top_scores.first().cloned().unwrap_or_default(),
+            top_scores,
+            bottom_scores,
+            score_distribution: Self::get_distribution(&results),
+            pair_metrics: PairMetrics::average(&exact),
+            slippage_metrics: PairMetrics::average(&slipped),
+            top_k_metrics: PairMetrics::average(&top_k),
+            element_recovery,
+            cloverleaf_recovery,
+            error_profile,
+            failures,
+            unscored,
+        }
+    }
 
-        let distribution = Self::get_distribution(&results);
+    pub fn new(seq_list: Vec<RnaSequence>) -> Self {
+        Self::with_reference(seq_list, Reference::Full)
+    }
 
-        (avg, std, max_score, min_score, top_five, bottom_five, distribution)
+    pub fn with_reference(seq_list: Vec<RnaSequence>, reference: Reference) -> Self {
+        Self::with_options(seq_list, Options { reference, ..Default::default() })
     }
 
-    pub fn new(seq_list: Vec<RnaSequence>) -> Self {
+    pub fn with_options(seq_list: Vec<RnaSequence>, options: Options) -> Self {
+        Self::run(seq_list, &Registry::default().folders(), options)
+    }
+
+    // Scores every folder of `folders` on the sequences.
+    pub fn run(seq_list: Vec<RnaSequence>, folders: &[Arc<dyn Folder>], options: Options) -> Self {
+        let seq_list: Vec<RnaSequence> = match options.reference {
+            Reference::Full => seq_list,
+            Reference::Nested => seq_list.iter().map(|seq| seq.nested()).collect(),
+        };
 
         let max = seq_list.len() as i32;
 
-        let ( vienna_avg, 
-              std_dev_vienna, 
-              max_v, 
-              min_v, 
-              top_five_v, 
-              bottom_five_v, 
-              distribution_v ) = Self::get_data(seq_list.clone(), "vienna", max.clone());
-
-        let ( nussinov_avg, 
-              std_dev_nussinov, 
-              max_n, 
-              min_n, 
-              top_five_n, 
-              bottom_five_n, 
-              distribution_n ) = Self::get_data(seq_list.clone(), "nussinov", max.clone());
-
-        Benchmark { vienna_avg: vienna_avg, 
-                    nussinov_avg: nussinov_avg, 
-                    quantity: max, 
-                    max_nussinov: max_n, 
-                    min_nussinov: min_n, 
-                    std_nussinov: std_dev_nussinov,
-                    top_scores_nussinov: top_five_n,
-                    bottom_scores_nussinov: bottom_five_n,
-                    score_distribution_nussinov: distribution_n,
-                    max_vienna: max_v, 
-                    min_vienna: min_v,
-                    std_vienna: std_dev_vienna,
-                    top_scores_vienna: top_five_v,
-                    bottom_scores_vienna: bottom_five_v,
-                    score_distribution_vienna: distribution_v }
+        let results = folders
+            .iter()
+            .map(|folder| (folder.name().to_string(), Self::get_data(&seq_list, folder.as_ref(), options)))
+            .collect();
+
+        Benchmark { metric: options.metric,
+                    slippage: options.slippage,
+                    helix_threshold: options.helix_threshold,
+                    top_k: options.top_k,
+                    results,
+                    quantity: max }
+    }
+
+    // One benchmark of `folders` per family of the dataset.
+    pub fn per_family(dataset: &Dataset, folders: &[Arc<dyn Folder>], options: &Options) -> BTreeMap<String, Self> {
+        dataset
+            .by_family()
+            .into_iter()
+            .map(|(family, seq_list)| (family, Self::run(seq_list, folders, *options)))
+            .collect()
+    }
+
+    pub fn algorithms(&self) -> Vec<&str> {
+        self.results.keys().map(String::as_str).collect()
+    }
+
+    pub fn results(&self, algo: &str) -> Result<&AlgorithmResults, Box<dyn std::error::Error>> {
+        self.results
+            .get(algo)
+            .ok_or_else(|| format!("No results for {}, algorithms run: {}", algo, self.algorithms().join(", ")).into())
+    }
+
+    // Width of the longest algorithm label, for aligned columns.
+    fn label_width(&self) -> usize {
+        self.results.keys().map(|algo| algo.len() + 1).max().unwrap_or(0)
     }
 
     fn max_strnum_len(distrib: [i32; 5]) -> i32 {
@@ -177,18 +363,7 @@ impl Benchmark {
         max_len
     }
 
-    pub fn score_distribution(&self, algo: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let distribution;
-
-        if algo == "vienna" {
-            distribution = self.score_distribution_vienna;
-        } else if algo  == "nussinov" {
-            distribution = self.score_distribution_nussinov;
-        } else {
-            panic!("Unknown algorithm: {}", algo);
-        }
-
-        println!("--- Score Distribution ({}) ---", algo);
+    pub fn score_distribution(&self) {
         let fraction = [
             "0-20%   ",
             "20-40%  ",
@@ -197,87 +372,250 @@ impl Benchmark {
             "80-100% "
         ];
         let sp_char = "█";
-        let max_strnum = Self::max_strnum_len(distribution);
-        for i in 0..distribution.len() {
-            print!("{}| ", fraction[i]);
-            let space_loops = max_strnum - distribution[i].to_string().len() as i32 + 1;
-            for _j in 0..space_loops {
-                print!(" ")
-            }
-            print!("{} | ", distribution[i]);
-            for _j in 0..distribution[i] {
-                print!("{}", sp_char);
+        for (algo, results) in &self.results {
+            let distribution = results.score_distribution;
+            println!("--- Score Distribution ({}) ---", algo);
+            let max_strnum = Self::max_strnum_len(distribution);
+            for (label, &count) in fraction.iter().zip(&distribution) {
+                print!("{}| ", label);
+                let space_loops = max_strnum - count.to_string().len() as i32 + 1;
+                for _j in 0..space_loops {
+                    print!(" ")
+                }
+                print!("{} | ", count);
+                for _j in 0..count {
+                    print!("{}", sp_char);
+                }
+                println!();
             }
             println!();
         }
-        println!();
-        Ok(())
     }
 
-    pub fn min_max(&self, algo: &str) {
-        if algo == "nussinov" || algo == "all" {
-            println!("Nussinov:  min={:.0}% | max={:.0}% | std={:.0}%", self.min_nussinov.1, self.max_nussinov.1, self.std_nussinov);
+    pub fn min_max(&self) {
+        let width = self.label_width() + 1;
+        for (algo, results) in &self.results {
+            println!("{:<width$}min={:.0}% | max={:.0}% | std={:.0}%", format!("{}:", algo), results.min.1, results.max.1, results.std);
         }
-        if algo == "vienna" || algo == "all" {
-            println!("ViennaRNA: min={:.0}% | max={:.0}% | std={:.0}%", self.min_vienna.1, self.max_vienna.1, self.std_vienna);
+    }
+
+    pub fn top_five(&self) {
+        for (algo, results) in &self.results {
+            println!("----- Top five {} -----", algo);
+            for (i, top) in results.top_scores.iter().enumerate() {
+                println!("{}. {:.1}% {}", i + 1, top.1, top.0);
+            }
+            println!();
         }
     }
 
-    pub fn top_five(&self, algo: &str) {
-        if algo == "nussinov" || algo == "all" {
-            println!("----- Top five Nussinov -----");
-            for i in 1..=5 {
-                let top = &self.top_scores_nussinov[i-1];
-                println!("{}. {:.1}% {}", i, top.1, top.0);
+    pub fn bottom_five(&self) {
+        for (algo, results) in &self.results {
+            println!("----- Worst five {} -----", algo);
+            for (i, bottom) in results.bottom_scores.iter().enumerate() {
+                println!("{}. {:.1}% {}", i + 1, bottom.1, bottom.0);
             }
+            println!();
         }
-        if algo == "vienna" || algo == "all" {
-            println!("----- Top five ViennaRNA -----");
-            for i in 1..=5 {
-                let top = &self.top_scores_vienna[i-1];
-                println!("{}. {:.1}% {}", i, top.1, top.0);
+    }
+
+    fn print_pair_metrics(label: &str, width: usize, m: &PairMetrics) {
+        println!("{:<width$}SEN={:.2}% | PPV={:.2}% | F1={:.2}% | MCC={:.3} | TP={} FP={} FN={}", label, m.sensitivity * 100.0, m.ppv * 100.0, m.f1 * 100.0, m.mcc, m.tp, m.fp, m.fn_);
+    }
+
+    pub fn pair_metrics(&self) {
+        let top_k_label = if self.top_k > 1 { format!(" top-{}:", self.top_k).len() } else { 0 };
+        let width = self.label_width() + 7.max(top_k_label + 1);
+        for (algo, results) in &self.results {
+            Self::print_pair_metrics(&format!("{}:", algo), width, &results.pair_metrics);
+            if self.slippage > 0 {
+                Self::print_pair_metrics(&format!("{} +/-{}:", algo, self.slippage), width, &results.slippage_metrics);
+            }
+            if self.top_k > 1 {
+                Self::print_pair_metrics(&format!("{} top-{}:", algo, self.top_k), width, &results.top_k_metrics);
             }
         }
     }
-    pub fn bottom_five(&self, algo: &str) {
-        if algo == "nussinov" || algo == "all" {
-            println!("----- Worst five Nussinov -----");
-            for i in 1..=5 {
-                let bottom = &self.bottom_scores_nussinov[i-1];
-                println!("{}. {:.1}% {}", i, bottom.1, bottom.0);
+
+    pub fn element_recovery(&self) {
+        for (algo, results) in &self.results {
+            println!("{}:", algo);
+            let recovery = &results.element_recovery;
+            for (kind, name) in REPORTED_ELEMENTS {
+                let counts = recovery.get(kind);
+                if counts.total == 0 {
+                    continue;
+                }
+                println!("  {:<16}{:>5}/{:<5} ({:.1}%)", name, counts.found, counts.total, counts.rate() * 100.0);
+                let sizes: Vec<String> = recovery
+                    .by_size
+                    .range((kind, 0)..=(kind, SIZE_CAP))
+                    .map(|(&(_, size), counts)| {
+                        let size = if size == SIZE_CAP { format!("{}+", SIZE_CAP) } else { size.to_string() };
+                        format!("{}: {}/{}", size, counts.found, counts.total)
+                    })
+                    .collect();
+                println!("    by size  {}", sizes.join(" | "));
             }
         }
-        if algo == "vienna" || algo == "all" {
-            println!("----- Worst five ViennaRNA -----");
-            for i in 1..=5 {
-                let bottom = &self.bottom_scores_vienna[i-1];
-                println!("{}. {:.1}% {}", i, bottom.1, bottom.0);
+    }
+
+    // References that are not a cloverleaf are left out.
+    pub fn cloverleaf_recovery(&self) {
+        for (algo, results) in &self.results {
+            let recovery = &results.cloverleaf_recovery;
+            let rows = [
+                ("cloverleaf".to_string(), recovery.cloverleaf),
+                (Arm::Acceptor.to_string(), recovery.get(Arm::Acceptor)),
+                (Arm::D.to_string(), recovery.get(Arm::D)),
+                (Arm::Anticodon.to_string(), recovery.get(Arm::Anticodon)),
+                (Arm::Variable.to_string(), recovery.get(Arm::Variable)),
+                (Arm::T.to_string(), recovery.get(Arm::T)),
+            ];
+            println!("{}:", algo);
+            for (name, counts) in rows {
+                if counts.total > 0 {
+                    println!("  {:<16}{:>5}/{:<5} ({:.1}%)", name, counts.found, counts.total, counts.rate() * 100.0);
+                }
             }
         }
     }
 
+    // Error rate of every aligned position, insertions included.
+    pub fn error_profile(&self) {
+        for (algo, results) in &self.results {
+            let profile = &results.error_profile;
+            println!("--- Positional error profile ({}, {} sequences) ---", algo, profile.sequences);
+            profile.print_table();
+            println!();
+        }
+    }
+
+    pub fn error_sparklines(&self) {
+        let width = self.label_width() + 1;
+        for (algo, results) in &self.results {
+            println!("{:<width$}{}", format!("{}:", algo), results.error_profile.sparkline());
+        }
+        if let Some(results) = self.results.values().next() {
+            println!("{:<width$}{}", "", results.error_profile.ruler());
+        }
+    }
+
     pub fn repr(&self) {
-        println!("=========== Benchmark matching scores for VIENNARNA and NUSSINOV algo ==========");
-        println!("Average Match Score for nussinov:  {:.2}%", self.nussinov_avg);
-        println!("Average Match Score for ViennaRNA: {:.2}%", self.vienna_avg);
+        let algorithms = self.algorithms().join(", ");
+        let first = self.results.values().next();
+        let cloverleaves = first.map_or(0, |results| results.cloverleaf_recovery.cloverleaf.total);
+        let profiled = first.map_or(0, |results| results.error_profile.sequences);
+        let width = self.label_width() + 1;
+
+        println!("=========== Benchmark of {} ({}) ==========", algorithms, self.metric);
+        for (algo, results) in &self.results {
+            println!("Average {} for {:<width$}{:.2}%", self.metric, format!("{}:", algo), results.avg);
+        }
         println!("Total samples number for each: {}", self.quantity);
+        for (algo, results) in self.results.iter().filter(|(_, results)| results.unscored > 0) {
+            println!("Left out of the {} of {}: {} without a reference", self.metric, algo, results.unscored);
+        }
+        for (algo, results) in self.results.iter().filter(|(_, results)| !results.failures.is_empty()) {
+            println!("Failed on {} sequence(s) with {}, left out:", results.failures.len(), algo);
+            for (id, error) in &results.failures {
+                println!("  {}: {}", id, error);
+            }
+        }
+        for (algo, results) in self.results.iter().filter(|(_, results)| !results.parameters.is_empty()) {
+            println!("Parameters of {:<width$}{}", format!("{}:", algo), results.parameters);
+        }
+        println!();
+        println!("------ Base pair metrics (mean per sequence) ------");
+        self.pair_metrics();
+        println!();
+        println!("------ Recovered elements (helix found at >= {:.0}% of its pairs) ------", self.helix_threshold * 100.0);
+        self.element_recovery();
+        println!();
+        println!("------ tRNA cloverleaf ({} of {} references are cloverleaves) ------", cloverleaves, self.quantity);
+        self.cloverleaf_recovery();
+        println!();
+        println!("------ Wrong pairing state by position ({} sequences, ▁ never to █ always) ------", profiled);
+        self.error_sparklines();
         println!();
         println!("----------- Min / Max Scores -----------");
-        self.min_max("all");
+        self.min_max();
         println!();
 
-        let _ = self.score_distribution("vienna");
-        let _ = self.score_distribution("nussinov");
+        self.score_distribution();
 
         println!("Quantity: {}", self.quantity);
 
-        self.top_five("nussinov");
-        println!();
-        self.bottom_five("nussinov");
-        println!();
-        self.top_five("vienna");
-        println!();
-        self.bottom_five("vienna");
+        self.top_five();
+        self.bottom_five();
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::tests::Fixed;
+
+    fn fixed(fold: &'static str) -> Vec<Arc<dyn Folder>> {
+        vec![Arc::new(Fixed(fold))]
+    }
+
+    #[test]
+    fn sequences_without_reference_are_left_out() {
+        let seq_list = vec![
+            RnaSequence::new("half", "((....))", "GGAAAACC"),
+            RnaSequence::new("exact", "(.(..).)", "GGAAAACC"),
+            RnaSequence::new("no reference", "", "GGAAAACC"),
+        ];
+        let bench = Benchmark::run(seq_list, &fixed("(.(..).)"), Options { metric: Metric::F1, ..Default::default() });
+        let results = bench.results("fixed").unwrap();
+        assert_eq!(results.unscored, 1);
+        assert!((results.avg - 75.0).abs() < 1e-9);
+        assert!((results.avg - results.pair_metrics.f1 * 100.0).abs() < 1e-9);
+        assert_eq!(results.score_distribution.iter().sum::<i32>(), 2);
+        assert!(bench.results("nussinov").is_err());
+    }
+
+    #[test]
+    fn cloverleaf_metric_leaves_out_other_references() {
+        use crate::trna::tests::{FOLD, SEQ};
+        let seq_list = vec![RnaSequence::new("trna", FOLD, SEQ), RnaSequence::new("unpaired", &".".repeat(SEQ.len()), SEQ)];
+        let bench = Benchmark::run(seq_list, &fixed(FOLD), Options { metric: Metric::Cloverleaf, ..Default::default() });
+        let results = bench.results("fixed").unwrap();
+        assert_eq!((results.unscored, results.avg, results.failures.len()), (1, 100.0, 0));
+        assert_eq!(results.cloverleaf_recovery.cloverleaf, crate::elements::Recovery { found: 1, total: 1 });
+    }
+
+    #[test]
+    fn one_benchmark_per_family() {
+        use crate::io::DatasetEntry;
+        use crate::nussinov::Nussinov;
+        let entry = |family: &str, id: &str, fold: &str, seq: &str| DatasetEntry {
+            family: family.to_string(),
+            path: Default::default(),
+            sequence: RnaSequence::new(id, fold, seq),
+        };
+        let dataset = Dataset {
+            entries: vec![
+                entry("5S", "a", "((....))", "GGAAAACC"),
+                entry("tRNA", "b", "((((...))))", "GGGGAAACCCC"),
+                entry("5S", "c", "((....))", "GCAAAAGC"),
+            ],
+            skipped: Vec::new(),
+        };
+        let folders: Vec<Arc<dyn Folder>> = vec![Arc::new(Fixed("((....))")), Arc::new(Nussinov::<u32>::default())];
+        let benches = Benchmark::per_family(&dataset, &folders, &Options::default());
+
+        assert_eq!(benches.keys().collect::<Vec<_>>(), ["5S", "tRNA"]);
+        assert_eq!(benches["5S"].algorithms(), ["fixed", "nussinov"]);
+        let fixed_5s = benches["5S"].results("fixed").unwrap();
+        assert_eq!((fixed_5s.avg, fixed_5s.failures.len()), (100.0, 0));
+        // the fixed structure does not fit the tRNA family's sequence
+        let fixed_trna = benches["tRNA"].results("fixed").unwrap();
+        assert_eq!(fixed_trna.failures.len(), 1);
+        assert_eq!(fixed_trna.failures[0].0, "b");
+        assert!(benches["tRNA"].results("nussinov").unwrap().failures.is_empty());
+
+        assert!(Benchmark::per_family(&Dataset::default(), &folders, &Options::default()).is_empty());
+    }
+}
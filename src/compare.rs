@@ -1,13 +1,97 @@
+use crate::elements::ElementRecovery;
+use crate::folder::{Folder, Registry};
 use crate::io::RnaSequence;
-use crate::nussinov::Matrix;
+use crate::profile::{Axis, ErrorProfile};
+use crate::structure::SecondaryStructure;
+use crate::trna::{Cloverleaf, CloverleafRecovery};
 
-use std::os::raw::c_char;
-use std::os::raw::c_void;
-use std::ffi::{CString, CStr};
+// Base pair level comparison of a prediction with the experimental structure.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PairMetrics {
+    pub tp: usize,
+    pub fp: usize,
+    pub fn_: usize,
+    pub sensitivity: f64,
+    pub ppv: f64,
+    pub f1: f64,
+    pub mcc: f64,
+}
+
+fn ratio(num: f64, den: f64) -> f64 {
+    if den == 0.0 { 0.0 } else { num / den }
+}
+
+impl PairMetrics {
+    pub fn new(tp: usize, fp: usize, fn_: usize, len: usize) -> Self {
+        // both structures without pairs: nothing to miss, nothing wrong
+        if tp + fp + fn_ == 0 {
+            return PairMetrics { sensitivity: 1.0, ppv: 1.0, f1: 1.0, mcc: 1.0, ..Default::default() };
+        }
+        let (tp_f, fp_f, fn_f) = (tp as f64, fp as f64, fn_ as f64);
+        // every other possible pair of the sequence is a true negative
+        let tn_f = (len * len.saturating_sub(1) / 2) as f64 - tp_f - fp_f - fn_f;
+        let sensitivity = ratio(tp_f, tp_f + fn_f);
+        let ppv = ratio(tp_f, tp_f + fp_f);
+        let f1 = ratio(2.0 * sensitivity * ppv, sensitivity + ppv);
+        let mcc = ratio(
+            tp_f * tn_f - fp_f * fn_f,
+            ((tp_f + fp_f) * (tp_f + fn_f) * (tn_f + fp_f) * (tn_f + fn_f)).sqrt(),
+        );
+        PairMetrics { tp, fp, fn_, sensitivity, ppv, f1, mcc }
+    }
+
+    // Positions beyond the end of either structure are compared as unpaired.
+    pub fn from_structures(reference: &SecondaryStructure, predicted: &SecondaryStructure) -> Self {
+        Self::from_structures_with_slippage(reference, predicted, 0)
+    }
+
+    // Mathews et al. convention: a pair (i, j) also counts as found when the
+    // other structure pairs i with j +/- slippage, or j with i +/- slippage.
+    // Sensitivity is then the share of reference pairs found and PPV the share
+    // of predicted pairs found, which may no longer be equal counts.
+    pub fn from_structures_with_slippage(reference: &SecondaryStructure, predicted: &SecondaryStructure, slippage: usize) -> Self {
+        // (pairs in structure, pairs of structure found in other)
+        let count_found = |structure: &SecondaryStructure, other: &SecondaryStructure| {
+            let mut pairs = 0;
+            let mut found = 0;
+            for (i, j) in structure.pairs() {
+                pairs += 1;
+                let slipped_j = other.partner(i).is_some_and(|k| k > i && k.abs_diff(j) <= slippage);
+                let slipped_i = other.partner(j).is_some_and(|k| k < j && k.abs_diff(i) <= slippage);
+                if slipped_j || slipped_i {
+                    found += 1;
+                }
+            }
+            (pairs, found)
+        };
+        let (predicted_pairs, tp) = count_found(predicted, reference);
+        let (reference_pairs, reference_found) = count_found(reference, predicted);
 
-unsafe extern "C" {
-    fn vienna_fold(seq: *const c_char) -> *const c_char;
-    fn free_vienna_fold_result(ptr: *mut c_void);
+        let len = reference.len().max(predicted.len());
+        let mut metrics = Self::new(tp, predicted_pairs - tp, reference_pairs - reference_found, len);
+        if reference_pairs > 0 && reference_found != tp {
+            metrics.sensitivity = reference_found as f64 / reference_pairs as f64;
+            metrics.f1 = ratio(2.0 * metrics.sensitivity * metrics.ppv, metrics.sensitivity + metrics.ppv);
+        }
+        metrics
+    }
+
+    // Rates averaged over the sequences, pair counts summed.
+    pub fn average(metrics: &[PairMetrics]) -> Self {
+        if metrics.is_empty() {
+            return PairMetrics::default();
+        }
+        let n = metrics.len() as f64;
+        PairMetrics {
+            tp: metrics.iter().map(|m| m.tp).sum(),
+            fp: metrics.iter().map(|m| m.fp).sum(),
+            fn_: metrics.iter().map(|m| m.fn_).sum(),
+            sensitivity: metrics.iter().map(|m| m.sensitivity).sum::<f64>() / n,
+            ppv: metrics.iter().map(|m| m.ppv).sum::<f64>() / n,
+            f1: metrics.iter().map(|m| m.f1).sum::<f64>() / n,
+            mcc: metrics.iter().map(|m| m.mcc).sum::<f64>() / n,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -15,14 +99,21 @@ pub struct Score {
     id: String,
     seq: String,
     algo: String,
-    exp_fold: String,
-    fold: String,
+    reference: Option<SecondaryStructure>,
+    structure: SecondaryStructure,
     score: f32,
+    metrics: Option<PairMetrics>,
 }
 
 
 
 impl Score {
+    // None when there is no usable experimental structure.
+    fn get_metrics_of(seq: &RnaSequence, structure: &SecondaryStructure) -> Option<PairMetrics> {
+        let reference = seq.structure.as_ref()?;
+        Some(PairMetrics::from_structures(reference, structure))
+    }
+
     fn get_accuracy(exp_fold: &str, fold: &str) -> f32 {
         // assert_eq!(exp_fold.len(), fold.len());
         let mut matches = 0;
@@ -30,49 +121,28 @@ impl Score {
             if exp_fold.as_bytes()[i] == fold.as_bytes()[i] { matches += 1;
             }
         }
-        let accuracy = ((matches as f32) / (fold.len() as f32)) * 100.0;
-        accuracy
-    }
-
-    fn get_nussinov(seq: &RnaSequence, algo: String) -> Result<Self, Box<dyn std::error::Error>> {
-        let matrix = Matrix::new(seq.to_string());
-        //matrix.print_mat();
-        let _pairs = matrix.traceback();
-        //println!("here2");
-        let fold = matrix.predict_fold();
-        //println!("here3");
-        let accuracy = Self::get_accuracy(&seq.exp_fold, &fold);
-        //println!("here4");
-
-        Ok(Score { id: seq.get_id(), seq: seq.to_string(), algo: algo, exp_fold: seq.exp_fold.clone(), fold: fold, score: accuracy })
+        ((matches as f32) / (fold.len() as f32)) * 100.0
     }
 
-
-    fn get_vienna(seq: &RnaSequence, algo: String) -> Result<Self, Box<dyn std::error::Error>> {
-        let fold = unsafe {
-            let c_string = CString::new(seq.to_string()).expect("CString::new failed");
-            let result_ptr = vienna_fold(c_string.as_ptr());
-            if result_ptr.is_null() {
-                panic!("pointer is null");
-            }
-            let result_str = CStr::from_ptr(result_ptr).to_str().unwrap().to_string();
-            free_vienna_fold_result(result_ptr as *mut c_void);
-            result_str
-        };
-        let accuracy = Self::get_accuracy(&seq.exp_fold, &fold);
-
-        Ok(Score { id: seq.get_id(), seq: seq.to_string(), algo: algo, exp_fold: seq.exp_fold.clone(), fold: fold, score: accuracy })
+    // Predicts with the algorithm registered under `algo` in the default
+    // registry (nussinov, vienna).
+    pub fn new(seq: RnaSequence, algo: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let folder = Registry::default().get(algo)?;
+        Self::with_folder(&seq, folder.as_ref())
     }
 
-
-    pub fn new(seq: RnaSequence, algo: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        if algo == "nussinov" {
-            return Self::get_nussinov(&seq, algo.to_string());
-        } else if algo == "vienna" {
-            return Self::get_vienna(&seq, algo.to_string());
-        } else {
-            panic!("wrong algorithm!\nOnly 'nussinov' and 'vienna' accepted\nYou entered: {}", algo);
+    pub fn with_folder(seq: &RnaSequence, folder: &dyn Folder) -> Result<Self, Box<dyn std::error::Error>> {
+        let structure = folder.fold(seq)?;
+        if structure.len() != seq.sizeof() {
+            return Err(format!("{} predicted {} positions for the {} nt of {}", folder.name(), structure.len(), seq.sizeof(), seq.get_id()).into());
+        }
+        if let Some(reference) = seq.structure.as_ref().filter(|reference| reference.len() != seq.sizeof()) {
+            return Err(format!("Experimental structure of {} has {} positions for {} nt", seq.get_id(), reference.len(), seq.sizeof()).into());
         }
+        let accuracy = Self::get_accuracy(&seq.exp_fold()?, &structure.to_dot_bracket()?);
+        let metrics = Self::get_metrics_of(seq, &structure);
+
+        Ok(Score { id: seq.get_id(), seq: seq.to_string(), algo: folder.name().to_string(), reference: seq.structure.clone(), structure, score: accuracy, metrics })
     }
     
     pub fn get_id(&self) -> Result<&str, Box<dyn std::error::Error>> {
@@ -83,17 +153,69 @@ impl Score {
         Ok(&self.seq)
     }
 
+    pub fn get_fold(&self) -> Result<String, Box<dyn std::error::Error>> {
+        self.structure.to_dot_bracket()
+    }
+
+    pub fn get_structure(&self) -> Result<&SecondaryStructure, Box<dyn std::error::Error>> {
+        Ok(&self.structure)
+    }
+
     pub fn get_score(&self) -> Result<f64, Box<dyn std::error::Error>> {
         Ok(self.score as f64)
     }
 
+    pub fn get_metrics(&self) -> Result<&PairMetrics, Box<dyn std::error::Error>> {
+        self.metrics
+            .as_ref()
+            .ok_or_else(|| format!("No pair metrics for {}: missing experimental structure", self.id).into())
+    }
+
+    fn reference(&self) -> Result<&SecondaryStructure, Box<dyn std::error::Error>> {
+        self.reference
+            .as_ref()
+            .ok_or_else(|| format!("No experimental structure for {}", self.id).into())
+    }
+
+    pub fn get_slippage_metrics(&self, slippage: usize) -> Result<PairMetrics, Box<dyn std::error::Error>> {
+        let reference = self.reference()?;
+        Ok(PairMetrics::from_structures_with_slippage(reference, &self.structure, slippage))
+    }
+
+    pub fn get_element_recovery(&self, helix_threshold: f64) -> Result<ElementRecovery, Box<dyn std::error::Error>> {
+        let reference = self.reference()?;
+        Ok(ElementRecovery::compare(reference, &self.structure, helix_threshold))
+    }
+
+    pub fn get_cloverleaf_recovery(&self, helix_threshold: f64) -> Result<CloverleafRecovery, Box<dyn std::error::Error>> {
+        let reference = self.reference()?;
+        let cloverleaf = Cloverleaf::find(reference, &self.seq)
+            .ok_or_else(|| format!("Experimental structure of {} is not a cloverleaf", self.id))?;
+        Ok(cloverleaf.compare(&self.structure, helix_threshold))
+    }
+
+    pub fn get_error_profile(&self, axis: Axis) -> Result<ErrorProfile, Box<dyn std::error::Error>> {
+        let reference = self.reference()?;
+        ErrorProfile::compare(reference, &self.structure, axis)
+            .ok_or_else(|| format!("No {:?} coordinates for {}", axis, self.id).into())
+    }
+
     pub fn repr(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if self.exp_fold.len() > 0 && self.fold.len() > 0 && self.score >= 0.0 && self.algo.len() > 0 {
+        if !self.structure.is_empty() && self.score >= 0.0 && !self.algo.is_empty() {
+            let fold = self.structure.to_dot_bracket()?;
+            // prediction-only run, nothing to compare against
+            let Some(reference) = &self.reference else {
+                println!("Algorithm used: {}", self.algo);
+                println!("Algorithmic result:  {}", fold);
+                return Ok(());
+            };
+            let exp_fold = reference.to_dot_bracket()?;
+
             let mut matches = String::new();
-            let exp_fold_bytes = self.exp_fold.as_bytes();
-            let algo_fold_bytes = self.fold.as_bytes();
+            let exp_fold_bytes = exp_fold.as_bytes();
+            let algo_fold_bytes = fold.as_bytes();
 
-            for i in 0..algo_fold_bytes.len() {
+            for i in 0..algo_fold_bytes.len().min(exp_fold_bytes.len()) {
                 if exp_fold_bytes[i] == algo_fold_bytes[i] {
                     matches.push('|');
                 } else {
@@ -102,10 +224,17 @@ impl Score {
             }
 
             println!("Algorithm used: {}", self.algo);
-            println!("Experimental result: {}", self.exp_fold);
-            println!("Algorithmic result:  {}", self.fold);
+            println!("Experimental result: {}", exp_fold);
+            println!("Algorithmic result:  {}", fold);
             println!("Matches:             {}", matches);
             println!("Match score:         {:.2}%", self.score);
+            if let Some(m) = &self.metrics {
+                println!("Base pairs:          TP={} FP={} FN={}", m.tp, m.fp, m.fn_);
+                println!("Pair metrics:        SEN={:.2}% PPV={:.2}% F1={:.2}% MCC={:.3}", m.sensitivity * 100.0, m.ppv * 100.0, m.f1 * 100.0, m.mcc);
+            }
+            if let Ok(m) = self.get_slippage_metrics(1) {
+                println!("Slippage +/-1:       SEN={:.2}% PPV={:.2}% F1={:.2}% MCC={:.3}", m.sensitivity * 100.0, m.ppv * 100.0, m.f1 * 100.0, m.mcc);
+            }
             
             Ok(())
         } else {
@@ -114,3 +243,72 @@ impl Score {
     }
 }
 
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn structure(fold: &str) -> SecondaryStructure {
+        SecondaryStructure::from_dot_bracket(fold).unwrap()
+    }
+
+    // Predicts a fixed structure whatever the sequence.
+    pub(crate) struct Fixed(pub(crate) &'static str);
+
+    impl Folder for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn fold(&self, _: &RnaSequence) -> Result<SecondaryStructure, Box<dyn std::error::Error>> {
+            Ok(structure(self.0))
+        }
+    }
+
+    #[test]
+    fn pair_counts_and_rates() {
+        // (0,7) found, (1,6) missed, (2,5) wrong; 28 possible pairs
+        let m = PairMetrics::from_structures(&structure("((....))"), &structure("(.(..).)"));
+        assert_eq!((m.tp, m.fp, m.fn_), (1, 1, 1));
+        assert_eq!((m.sensitivity, m.ppv, m.f1), (0.5, 0.5, 0.5));
+        assert!((m.mcc - 24.0 / 52.0).abs() < 1e-12);
+
+        let unpaired = PairMetrics::from_structures(&structure("...."), &structure("...."));
+        assert_eq!((unpaired.f1, unpaired.mcc), (1.0, 1.0));
+        let nothing_found = PairMetrics::from_structures(&structure("((....))"), &structure("........"));
+        assert_eq!((nothing_found.fn_, nothing_found.f1), (2, 0.0));
+    }
+
+    #[test]
+    fn slipped_pairs_count_as_found() {
+        let reference = structure("((....))");
+        let shifted = structure("((...).)");
+        let exact = PairMetrics::from_structures(&reference, &shifted);
+        assert_eq!((exact.tp, exact.fp, exact.fn_), (1, 1, 1));
+        let slipped = PairMetrics::from_structures_with_slippage(&reference, &shifted, 1);
+        assert_eq!((slipped.tp, slipped.fp, slipped.fn_, slipped.f1), (2, 0, 0, 1.0));
+        // two positions off is not found with a slippage of 1
+        let far = PairMetrics::from_structures_with_slippage(&reference, &structure("((..).)."), 1);
+        assert_eq!(far.tp, 1);
+    }
+
+    #[test]
+    fn references_of_another_length_are_rejected() {
+        let seq = RnaSequence::new("short reference", "((...))", "GGAAAACC");
+        let e = Score::with_folder(&seq, &Fixed("((....))")).unwrap_err();
+        assert!(e.to_string().contains("7 positions for 8 nt"), "{}", e);
+    }
+
+    #[test]
+    fn no_metrics_without_a_reference() {
+        let seq = RnaSequence::new("no reference", "", "GGAAAACC");
+        let score = Score::with_folder(&seq, &Fixed("((....))")).unwrap();
+        assert!(score.get_metrics().is_err());
+        assert!(score.get_slippage_metrics(1).is_err());
+        assert!(score.get_element_recovery(0.5).is_err());
+
+        let seq = RnaSequence::new("reference", "((....))", "GGAAAACC");
+        let score = Score::with_folder(&seq, &Fixed("(.(..).)")).unwrap();
+        assert_eq!(score.get_metrics().unwrap().tp, 1);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::structure::SecondaryStructure;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ElementKind {
+    Stem,
+    Hairpin,
+    Bulge,
+    InteriorLoop,
+    Multiloop,
+    ExteriorLoop,
+}
+
+impl fmt::Display for ElementKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ElementKind::Stem => "stem",
+            ElementKind::Hairpin => "hairpin",
+            ElementKind::Bulge => "bulge",
+            ElementKind::InteriorLoop => "interior loop",
+            ElementKind::Multiloop => "multiloop",
+            ElementKind::ExteriorLoop => "exterior loop",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// One structural element. Positions are 0-based.
+// - stem: `pairs` are its stacked pairs from the outside in, `segments` its two strands
+// - loop: `pairs` are the pairs closing it, outer one first (the exterior
+//   loop has no outer one), `segments` its runs of unpaired positions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub kind: ElementKind,
+    pub pairs: Vec<(usize, usize)>,
+    pub segments: Vec<(usize, usize)>, // inclusive ranges
+}
+
+impl Element {
+    // Base pairs for a stem, unpaired nucleotides for a loop.
+    pub fn size(&self) -> usize {
+        match self.kind {
+            ElementKind::Stem => self.pairs.len(),
+            _ => self.segments.iter().map(|&(start, end)| end - start + 1).sum(),
+        }
+    }
+
+    pub fn start(&self) -> Option<usize> {
+        let segments = self.segments.iter().map(|&(start, _)| start);
+        let pairs = self.pairs.iter().map(|&(i, _)| i);
+        segments.chain(pairs).min()
+    }
+
+    pub fn contains(&self, position: usize) -> bool {
+        self.segments.iter().any(|&(start, end)| start <= position && position <= end)
+    }
+}
+
+type Ranges = Vec<(usize, usize)>;
+
+// Unpaired runs and branching pairs met going from `from` to `to` (inclusive)
+// at one nesting level.
+fn scan_level(structure: &SecondaryStructure, from: usize, to: usize) -> (Ranges, Ranges) {
+    let mut segments = Vec::new();
+    let mut branches = Vec::new();
+    let mut k = from;
+    while k <= to && k < structure.len() {
+        match structure.partner(k) {
+            Some(l) if l > k => {
+                branches.push((k, l));
+                k = l + 1;
+            }
+            _ => {
+                let start = k;
+                while k < to && structure.partner(k + 1).is_none() {
+                    k += 1;
+                }
+                segments.push((start, k));
+                k += 1;
+            }
+        }
+    }
+    (segments, branches)
+}
+
+// Splits a structure into stems and the loops between them. Pseudoknotted
+// pairs are left out first (see SecondaryStructure::nested), so their
+// positions count as unpaired in the loop they fall in.
+pub fn decompose(structure: &SecondaryStructure) -> Vec<Element> {
+    let structure = structure.nested();
+    let mut elements = Vec::new();
+
+    let (segments, pairs) = match structure.len() {
+        0 => (Vec::new(), Vec::new()),
+        len => scan_level(&structure, 0, len - 1),
+    };
+    elements.push(Element { kind: ElementKind::ExteriorLoop, pairs, segments });
+
+    for (i, j) in structure.pairs() {
+        // a stem starts at a pair that does not stack on an outer one
+        let stacked_on_outer = i > 0 && structure.partner(i - 1) == Some(j + 1);
+        if !stacked_on_outer {
+            let mut pairs = vec![(i, j)];
+            while let Some(&(a, b)) = pairs.last() {
+                if b > a + 2 && structure.partner(a + 1) == Some(b - 1) {
+                    pairs.push((a + 1, b - 1));
+                } else {
+                    break;
+                }
+            }
+            let &(a, b) = pairs.last().unwrap();
+            let segments = vec![(i, a), (b, j)];
+            elements.push(Element { kind: ElementKind::Stem, pairs, segments });
+        }
+
+        // the loop closed by (i, j), unless the next pair stacks on it
+        if j > i + 1 && structure.partner(i + 1) == Some(j - 1) {
+            continue;
+        }
+        let (segments, branches) = scan_level(&structure, i + 1, j - 1);
+        let kind = match branches.as_slice() {
+            [] => ElementKind::Hairpin,
+            [(k, l)] if *k == i + 1 || *l == j - 1 => ElementKind::Bulge,
+            [_] => ElementKind::InteriorLoop,
+            _ => ElementKind::Multiloop,
+        };
+        let mut pairs = vec![(i, j)];
+        pairs.extend(branches);
+        elements.push(Element { kind, pairs, segments });
+    }
+
+    elements.sort_by_key(|element| element.start());
+    elements
+}
+
+// bpRNA structure array: S stem, H hairpin, B bulge, I interior loop,
+// M multiloop, X exterior loop between stems and E the unpaired ends.
+pub fn structure_type(structure: &SecondaryStructure) -> String {
+    let mut types = vec!['E'; structure.len()];
+    let nested = structure.nested();
+    let first = nested.pairs().next().map(|(i, _)| i);
+    let last = nested.pairs().map(|(_, j)| j).max();
+
+    for element in decompose(structure) {
+        for &(start, end) in &element.segments {
+            for (position, c) in types.iter_mut().enumerate().take(end + 1).skip(start) {
+                *c = match element.kind {
+                    ElementKind::Stem => 'S',
+                    ElementKind::Hairpin => 'H',
+                    ElementKind::Bulge => 'B',
+                    ElementKind::InteriorLoop => 'I',
+                    ElementKind::Multiloop => 'M',
+                    ElementKind::ExteriorLoop => match (first, last) {
+                        (Some(first), Some(last)) if first < position && position < last => 'X',
+                        _ => 'E',
+                    },
+                };
+            }
+        }
+    }
+    types.into_iter().collect()
+}
+
+// Elements of one kind found out of those in the reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Recovery {
+    pub found: usize,
+    pub total: usize,
+}
+
+impl Recovery {
+    pub fn rate(&self) -> f64 {
+        if self.total == 0 { 0.0 } else { self.found as f64 / self.total as f64 }
+    }
+
+    fn add(&mut self, found: bool) {
+        self.total += 1;
+        if found {
+            self.found += 1;
+        }
+    }
+}
+
+// Sizes from this one on are counted together.
+pub const SIZE_CAP: usize = 10;
+
+// Reference elements recovered by a prediction, by kind and by kind and size.
+// The exterior loop is not counted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElementRecovery {
+    pub by_kind: BTreeMap<ElementKind, Recovery>,
+    pub by_size: BTreeMap<(ElementKind, usize), Recovery>,
+}
+
+impl ElementRecovery {
+    // A stem is found when at least `helix_threshold` (0 to 1) of its pairs
+    // are predicted. A loop is found when the stems around it are, and the
+    // prediction has a loop of the same kind over some of its positions.
+    pub fn compare(reference: &SecondaryStructure, predicted: &SecondaryStructure, helix_threshold: f64) -> Self {
+        let reference_elements = decompose(reference);
+        let predicted_elements = decompose(predicted);
+
+        // first position of each stem pair -> whether its stem was found
+        let mut stem_found: BTreeMap<usize, bool> = BTreeMap::new();
+        for stem in reference_elements.iter().filter(|element| element.kind == ElementKind::Stem) {
+            let predicted_pairs = stem.pairs.iter().filter(|&&(i, j)| predicted.partner(i) == Some(j)).count();
+            let found = predicted_pairs as f64 >= helix_threshold * stem.pairs.len() as f64;
+            for &(i, _) in &stem.pairs {
+                stem_found.insert(i, found);
+            }
+        }
+
+        let mut recovery = ElementRecovery::default();
+        for element in &reference_elements {
+            let found = match element.kind {
+                ElementKind::ExteriorLoop => continue,
+                ElementKind::Stem => stem_found[&element.pairs[0].0],
+                _ => {
+                    let stems_found = element.pairs.iter().all(|(i, _)| stem_found[i]);
+                    let overlapping = predicted_elements.iter().any(|other| {
+                        other.kind == element.kind
+                            && (element.segments.is_empty()
+                                || element.segments.iter().any(|&(start, end)| (start..=end).any(|k| other.contains(k))))
+                    });
+                    stems_found && overlapping
+                }
+            };
+            recovery.by_kind.entry(element.kind).or_default().add(found);
+            let size = element.size().min(SIZE_CAP);
+            recovery.by_size.entry((element.kind, size)).or_default().add(found);
+        }
+        recovery
+    }
+
+    pub fn merge(&mut self, other: &ElementRecovery) {
+        for (kind, counts) in &other.by_kind {
+            let entry = self.by_kind.entry(*kind).or_default();
+            entry.found += counts.found;
+            entry.total += counts.total;
+        }
+        for (key, counts) in &other.by_size {
+            let entry = self.by_size.entry(*key).or_default();
+            entry.found += counts.found;
+            entry.total += counts.total;
+        }
+    }
+
+    pub fn get(&self, kind: ElementKind) -> Recovery {
+        self.by_kind.get(&kind).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn structure(fold: &str) -> SecondaryStructure {
+        SecondaryStructure::from_dot_bracket(fold).unwrap()
+    }
+
+    fn kinds(fold: &str) -> Vec<(ElementKind, usize)> {
+        decompose(&structure(fold)).iter().map(|element| (element.kind, element.size())).collect()
+    }
+
+    #[test]
+    fn readme_example() {
+        let example = structure("..((((...))..((..))..))..");
+        assert_eq!(structure_type(&example), "EESSSSHHHSSMMSSHHSSMMSSEE");
+        use ElementKind::*;
+        assert_eq!(
+            kinds("..((((...))..((..))..)).."),
+            [(ExteriorLoop, 4), (Stem, 2), (Multiloop, 4), (Stem, 2), (Hairpin, 3), (Stem, 2), (Hairpin, 2)]
+        );
+        let multiloop = decompose(&example).into_iter().find(|element| element.kind == Multiloop).unwrap();
+        assert_eq!(multiloop.pairs, [(3, 21), (4, 10), (13, 18)]);
+        assert_eq!(multiloop.segments, [(11, 12), (19, 20)]);
+    }
+
+    #[test]
+    fn bulges_and_interior_loops() {
+        assert_eq!(structure_type(&structure("((.((...))))")), "SSBSSHHHSSSS");
+        assert_eq!(structure_type(&structure("((..((...))..))")), "SSIISSHHHSSIISS");
+        assert_eq!(structure_type(&structure("((...))..((...))")), "SSHHHSSXXSSHHHSS");
+        let interior = decompose(&structure("((..((...)).))")).into_iter().find(|element| element.kind == ElementKind::InteriorLoop).unwrap();
+        assert_eq!((interior.pairs.as_slice(), interior.size()), ([(1, 12), (4, 10)].as_slice(), 3));
+    }
+
+    #[test]
+    fn unpaired_and_pseudoknotted_positions() {
+        assert_eq!(kinds(""), [(ElementKind::ExteriorLoop, 0)]);
+        assert_eq!(structure_type(&structure(".....")), "EEEEE");
+        // the crossing [] pair is not decomposed, its positions are loop
+        assert_eq!(structure_type(&structure("((.[..))..]")), "SSHHHHSSEEE");
+    }
+
+    #[test]
+    fn recovered_elements() {
+        use ElementKind::*;
+        let reference = structure("..((((...))..((..))..))..");
+        let exact = ElementRecovery::compare(&reference, &reference, 0.5);
+        assert_eq!((exact.get(Stem), exact.get(Hairpin), exact.get(Multiloop)), (
+            Recovery { found: 3, total: 3 },
+            Recovery { found: 2, total: 2 },
+            Recovery { found: 1, total: 1 },
+        ));
+        assert_eq!(exact.by_size[&(Hairpin, 3)], Recovery { found: 1, total: 1 });
+
+        // the second hairpin's stem is lost, and with it the multiloop
+        let lost = ElementRecovery::compare(&reference, &structure("..((((...))..........)).."), 0.5);
+        assert_eq!((lost.get(Stem).found, lost.get(Hairpin).found, lost.get(Multiloop).found), (2, 1, 0));
+
+        // one pair of a two pair stem is enough at 0.5, not at 1
+        let half = structure("..((((...))...(..)...))..");
+        assert_eq!(ElementRecovery::compare(&reference, &half, 0.5).get(Stem).found, 3);
+        assert_eq!(ElementRecovery::compare(&reference, &half, 1.0).get(Stem).found, 2);
+
+        let mut merged = exact.clone();
+        merged.merge(&lost);
+        assert_eq!(merged.get(Multiloop), Recovery { found: 1, total: 2 });
+        assert_eq!(merged.get(Multiloop).rate(), 0.5);
+        assert_eq!(merged.get(Bulge).rate(), 0.0);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::io::RnaSequence;
+use crate::nussinov::Nussinov;
+use crate::structure::SecondaryStructure;
+use crate::vienna::ViennaRna;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+// A structure prediction algorithm. Folders are shared between the benchmark
+// threads, hence Send + Sync.
+pub trait Folder: Send + Sync {
+    // Name the algorithm is registered and reported under.
+    fn name(&self) -> &str;
+
+    // Parameters the predictions depend on, for the reports.
+    fn parameters(&self) -> String {
+        String::new()
+    }
+
+    fn fold(&self, seq: &RnaSequence) -> Result<SecondaryStructure, Box<dyn std::error::Error>>;
+
+    // Up to `k` candidate structures, best first, the first being the
+    // prediction of `fold` (none for k = 0). Folders that cannot rank
+    // alternatives give their prediction alone.
+    fn candidates(&self, seq: &RnaSequence, k: usize) -> Result<Vec<SecondaryStructure>, Box<dyn std::error::Error>> {
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        Ok(vec![self.fold(seq)?])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAlgorithm {
+    pub name: String,
+    pub known: Vec<String>,
+}
+
+impl fmt::Display for UnknownAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown algorithm '{}', registered: {}", self.name, self.known.join(", "))
+    }
+}
+
+impl std::error::Error for UnknownAlgorithm {}
+
+// Folders by name. The default registry holds Nussinov and ViennaRNA.
+#[derive(Clone)]
+pub struct Registry {
+    folders: BTreeMap<String, Arc<dyn Folder>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register(Nussinov::<u32>::default());
+        registry.register(ViennaRna);
+        registry
+    }
+}
+
+impl Registry {
+    pub fn empty() -> Self {
+        Registry { folders: BTreeMap::new() }
+    }
+
+    // Adds a folder, replacing any registered under the same name.
+    pub fn register(&mut self, folder: impl Folder + 'static) {
+        self.folders.insert(folder.name().to_string(), Arc::new(folder));
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<dyn Folder>, UnknownAlgorithm> {
+        self.folders.get(name).cloned().ok_or_else(|| UnknownAlgorithm {
+            name: name.to_string(),
+            known: self.names(),
+        })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.folders.keys().cloned().collect()
+    }
+
+    pub fn folders(&self) -> Vec<Arc<dyn Folder>> {
+        self.folders.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Score;
+    use crate::compare::tests::Fixed;
+    use crate::nussinov::NussinovParams;
+
+    #[test]
+    fn folders_by_name() {
+        let mut registry = Registry::default();
+        assert_eq!(registry.names(), ["nussinov", "vienna"]);
+        registry.register(Fixed("((....))"));
+        let seq = RnaSequence::new("seq", "", "GGAAAACC");
+        assert_eq!(registry.get("fixed").unwrap().fold(&seq).unwrap().to_dot_bracket().unwrap(), "((....))");
+
+        // same name, replaced
+        registry.register(Nussinov::new("fixed", NussinovParams::<u32>::default().with_min_loop(5)));
+        assert_eq!(registry.folders().len(), 3);
+        assert_eq!(registry.get("fixed").unwrap().parameters(), "min hairpin loop 5, pairs AU=1 CG=1 GU=1");
+    }
+
+    #[test]
+    fn unknown_algorithms_are_errors() {
+        let error = Registry::default().get("mfold").err().unwrap();
+        assert_eq!(error, UnknownAlgorithm { name: "mfold".to_string(), known: vec!["nussinov".to_string(), "vienna".to_string()] });
+        assert_eq!(error.to_string(), "Unknown algorithm 'mfold', registered: nussinov, vienna");
+        let seq = RnaSequence::new("seq", "((....))", "GGAAAACC");
+        assert!(Score::new(seq, "mfold").is_err_and(|e| e.to_string().starts_with("Unknown algorithm 'mfold'")));
+        assert!(Registry::empty().get("nussinov").is_err_and(|e| e.known.is_empty()));
+    }
+
+    #[test]
+    fn candidates_default_to_the_prediction() {
+        let seq = RnaSequence::new("seq", "", "GGAAAACC");
+        assert!(Fixed("((....))").candidates(&seq, 0).unwrap().is_empty());
+        assert_eq!(Fixed("((....))").candidates(&seq, 3).unwrap(), [Fixed("((....))").fold(&seq).unwrap()]);
+    }
+}
@@ -1,23 +1,88 @@
-use anyhow::{Result, Context};
-use std::fs;
+use crate::modifications;
+use crate::structure::{self, SecondaryStructure};
+use crate::trna::{Cloverleaf, SprinzlPosition};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+
+mod bpseq;
+mod ct;
+mod dataset;
+mod join;
+mod stockholm;
+pub use bpseq::{read_bpseq, write_bpseq, write_bpseq_reference};
+pub use ct::{read_ct, write_ct};
+pub use dataset::{Dataset, DatasetEntry};
+pub use join::{JoinReport, join_datasets, write_dataset};
+pub use stockholm::{StockholmAlignment, read_stockholm};
+
+// Base of a format without tRNAdb codes (CT, BPSEQ, Stockholm, plain FASTA):
+// T is read as U, not as ribothymidine, and IUPAC ambiguity codes and anything
+// else but A, C, G and U are an unknown N.
+fn plain_base(c: char) -> char {
+    match c {
+        'T' => 'U',
+        't' => 'u',
+        c if "ACGUacgu".contains(c) => c,
+        _ => 'N',
+    }
+}
+
+// Folding code of a position and, if modified, its original tRNAdb code.
+type Nucleotide = (u8, Option<char>);
+
+// An experimental structure that could not be read, kept as written so that
+// validation can report it and writers can give it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFold {
+    pub fold: String,
+    pub error: String,
+}
 
 #[derive(Debug, Clone)]
 pub struct RnaSequence {
     pub id: String,
-    pub sequence: Vec<u8>, // A=0, U=1, G=2, C=3
-    pub exp_fold: String,
+    pub sequence: Vec<u8>, // A=0, U=1, G=2, C=3, N=4
+    pub modifications: Vec<Option<char>>, // tRNAdb code at modified positions
+    pub structure: Option<SecondaryStructure>, // experimental structure, if known
+    pub invalid_fold: Option<InvalidFold>,     // experimental structure that could not be read
 }
 
 impl RnaSequence {
     pub fn new(id: &str, fold: &str, seq: &str) -> Self {
-        RnaSequence {
-            id: String::from(id),
-            sequence: Self::vecu8(seq).expect("RnaSequence::vecu8() fail."),
-            exp_fold: String::from(fold),
+        Self::try_new(id, fold, seq).expect("RnaSequence::try_new() fail.")
+    }
+
+    // `fold` is the experimental structure in dot-bracket, empty if unknown.
+    // A fold that cannot be read does not fail the record: it is kept in
+    // `invalid_fold`, for validation to flag.
+    pub fn try_new(id: &str, fold: &str, seq: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if fold.is_empty() {
+            return Self::with_structure(id, None, seq);
+        }
+        match SecondaryStructure::from_dot_bracket(fold) {
+            Ok(structure) => Self::with_structure(id, Some(structure), seq),
+            Err(e) => Ok(RnaSequence {
+                invalid_fold: Some(InvalidFold { fold: fold.to_string(), error: e.to_string() }),
+                ..Self::with_structure(id, None, seq)?
+            }),
         }
     }
 
-    fn vecu8(seq: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    pub fn with_structure(id: &str, structure: Option<SecondaryStructure>, seq: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (sequence, modifications) = Self::vecu8(seq)?.into_iter().unzip();
+        Ok(RnaSequence {
+            id: String::from(id),
+            sequence,
+            modifications,
+            structure,
+            invalid_fold: None,
+        })
+    }
+
+    // Modified nucleosides are folded as their parent base, the original
+    // tRNAdb code is kept in `modifications`.
+    fn vecu8(seq: &str) -> Result<Vec<Nucleotide>, Box<dyn std::error::Error>> {
         let cleaned: String = seq
             .chars()
             .filter(|&c| !c.is_whitespace() && c != '_')
@@ -25,14 +90,25 @@ impl RnaSequence {
 
         let mut vec_seq = Vec::with_capacity(cleaned.len());
         for c in cleaned.chars() {
-            let v = match c.to_ascii_uppercase() {
+            // in tRNAdb sequences T is ribothymidine (m5U), N and . an unknown
+            // nucleotide
+            let code = if c == 't' { 'T' } else { c };
+            let (base, modification) = match code.to_ascii_uppercase() {
+                'A' | 'U' | 'G' | 'C' | 'N' => (code.to_ascii_uppercase(), None),
+                '.' => ('N', None),
+                _ => match modifications::parent_base(code) {
+                    Some(parent) => (parent, Some(code)),
+                    None => return Err(format!("Invalid nucleotide: {}", c).into()),
+                },
+            };
+            let v = match base {
                 'A' => 0,
-                'U' | 'T' => 1,
+                'U' => 1,
                 'G' => 2,
                 'C' => 3,
-                _ => return Err(format!("Invalid nucleotide: {}", c).into()),
+                _ => 4,
             };
-            vec_seq.push(v);
+            vec_seq.push((v, modification));
         }
 
         Ok(vec_seq)
@@ -46,49 +122,507 @@ impl RnaSequence {
         self.sequence.len()
     }
 
-    pub fn to_string(&self) -> String {
-        self.sequence
+    pub fn has_structure(&self) -> bool {
+        self.structure.is_some()
+    }
+
+    pub fn exp_structure(&self) -> Result<&SecondaryStructure, Box<dyn std::error::Error>> {
+        if let Some(invalid) = &self.invalid_fold {
+            return Err(format!("Invalid structure for {}: {}", self.id, invalid.error).into());
+        }
+        self.structure
+            .as_ref()
+            .ok_or_else(|| format!("No experimental structure for {}", self.id).into())
+    }
+
+    // Experimental structure in dot-bracket, as written if it could not be
+    // read, empty if unknown.
+    pub fn exp_fold(&self) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(invalid) = &self.invalid_fold {
+            return Ok(invalid.fold.clone());
+        }
+        self.structure.as_ref().map_or(Ok(String::new()), |structure| structure.to_dot_bracket())
+    }
+
+    // Sprinzl coordinate of each position, from the experimental cloverleaf.
+    pub fn sprinzl_numbering(&self) -> Result<Vec<SprinzlPosition>, Box<dyn std::error::Error>> {
+        let cloverleaf = Cloverleaf::find(self.exp_structure()?, &self.to_string())
+            .ok_or_else(|| format!("Experimental structure of {} is not a cloverleaf", self.id))?;
+        Ok(cloverleaf.sprinzl_numbering(self.sizeof()))
+    }
+
+    pub fn has_pseudoknot(&self) -> bool {
+        self.structure.as_ref().is_some_and(|structure| structure.is_pseudoknotted())
+    }
+
+    // Same sequence with only the nested (pseudoknot-free) part of its
+    // structure, see SecondaryStructure::nested.
+    pub fn nested(&self) -> Self {
+        RnaSequence {
+            structure: self.structure.as_ref().map(|structure| structure.nested()),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modifications.iter().any(|m| m.is_some())
+    }
+
+    pub fn to_modified_string(&self) -> String {
+        self.to_string()
+            .chars()
+            .zip(&self.modifications)
+            .map(|(base, m)| m.unwrap_or(base))
+            .collect()
+    }
+}
+
+impl fmt::Display for RnaSequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seq: String = self.sequence
             .iter()
             .map(|&n| match n {
                 0 => 'A',
                 1 => 'U',
                 2 => 'G',
                 3 => 'C',
+                4 => 'N',
                 _ => '_',
             })
-            .collect()
+            .collect();
+        write!(f, "{}", seq)
     }
 }
 
 
 
 
-pub fn parse_fasta(filepath: &str, max: usize) -> Vec<RnaSequence> {
-    let content = fs::read_to_string(filepath)
-                        .with_context(|| format!("Failed to read FASTA file: {}", filepath));
-    let binding = content.expect("Error lines");
-    let mut lines = binding.lines();
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    Io(std::io::Error),
+    MissingHeader,
+    MissingSequence,
+    AmbiguousLayout,
+    InvalidSequence(String),
+    InvalidStructure(String),
+    InvalidLine(String),
+}
 
-    let mut sequence_list: Vec<RnaSequence> = Vec::new();
-    let mut count = 0 as usize;
-    while let (Some(id_line), Some(fold_line), Some(seq_line)) =
-                   (lines.next(), lines.next(), lines.next()) && count < max {
+#[derive(Debug)]
+pub struct ParseError {
+    pub source_name: String,
+    pub line: usize,
+    pub id: Option<String>,
+    pub kind: ParseErrorKind,
+}
 
-        if fold_line.contains('[') || fold_line.contains(']') {
-            continue;
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.source_name, self.line)?;
+        if let Some(id) = &self.id {
+            write!(f, "{}: ", id)?;
+        }
+        match &self.kind {
+            ParseErrorKind::Io(e) => write!(f, "I/O error: {}", e),
+            ParseErrorKind::MissingHeader => write!(f, "expected a '>' header line"),
+            ParseErrorKind::MissingSequence => write!(f, "record has no sequence line"),
+            ParseErrorKind::AmbiguousLayout => write!(f, "structure and sequence lines are interleaved"),
+            ParseErrorKind::InvalidSequence(msg) => write!(f, "{}", msg),
+            ParseErrorKind::InvalidStructure(msg) => write!(f, "invalid structure: {}", msg),
+            ParseErrorKind::InvalidLine(msg) => write!(f, "{}", msg),
         }
+    }
+}
 
-        if seq_line.contains('J') || seq_line.contains('4') {
-            continue;
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ParseErrorKind::Io(e) => Some(e),
+            _ => None,
         }
+    }
+}
 
-        let id       = id_line.trim_start_matches('>').trim();
-        let exp_fold = fold_line.trim();
-        let seq      = seq_line.trim();
-        
-        sequence_list.push(RnaSequence::new(id, exp_fold, seq));
-        count += 1;
-    };
-    sequence_list
+impl ParseError {
+    // I/O errors leave the reader in an unknown state, everything else only
+    // concerns a single record.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self.kind, ParseErrorKind::Io(_))
+    }
 }
 
+#[derive(Debug, Clone)]
+pub struct FastaRecord {
+    pub id: String,
+    pub line: usize,
+    pub seq_line: usize,
+    pub fold_line: Option<usize>,
+    pub exp_fold: String,
+    pub seq: String,
+}
+
+// Streaming reader for `>id` records holding a structure and a sequence in
+// either order, or a sequence only (plain FASTA, `exp_fold` is left empty).
+// Blank lines are ignored and both parts may be wrapped over several lines.
+pub struct FastaReader<R: BufRead> {
+    lines: Lines<R>,
+    source_name: String,
+    line_no: usize,
+    next_header: Option<(usize, String)>,
+    done: bool,
+    plain: bool,
+}
+
+impl FastaReader<BufReader<File>> {
+    pub fn open(filepath: &str) -> Result<Self, ParseError> {
+        let file = File::open(filepath).map_err(|e| ParseError {
+            source_name: filepath.to_string(),
+            line: 0,
+            id: None,
+            kind: ParseErrorKind::Io(e),
+        })?;
+        Ok(Self::new(BufReader::new(file), filepath))
+    }
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(reader: R, source_name: &str) -> Self {
+        FastaReader {
+            lines: reader.lines(),
+            source_name: source_name.to_string(),
+            line_no: 0,
+            next_header: None,
+            done: false,
+            plain: false,
+        }
+    }
+
+    // Reads sequences as plain nucleotides rather than tRNAdb codes, for
+    // files from other sources (T is U, ambiguity codes are N).
+    pub fn plain(mut self) -> Self {
+        self.plain = true;
+        self
+    }
+
+    fn error(&self, line: usize, id: Option<&str>, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            source_name: self.source_name.clone(),
+            line,
+            id: id.map(String::from),
+            kind,
+        }
+    }
+
+    // Next non-blank line with its number, or None at end of input.
+    fn next_line(&mut self) -> Option<Result<(usize, String), ParseError>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            match self.lines.next() {
+                None => {
+                    self.done = true;
+                    return None;
+                }
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(self.error(self.line_no + 1, None, ParseErrorKind::Io(e))));
+                }
+                Some(Ok(line)) => {
+                    self.line_no += 1;
+                    let line = line.trim();
+                    if !line.is_empty() {
+                        return Some(Ok((self.line_no, line.to_string())));
+                    }
+                }
+            }
+        }
+    }
+
+    fn build_record(&self, header_line: usize, id: &str, body: Vec<(usize, String)>) -> Result<FastaRecord, ParseError> {
+        let is_fold: Vec<bool> = body
+            .iter()
+            .map(|(_, line)| structure::looks_like_dot_bracket(line))
+            .collect();
+        let leading_folds = is_fold.iter().take_while(|&&f| f).count();
+        let trailing_folds = is_fold.iter().rev().take_while(|&&f| f).count();
+
+        // id/fold/seq, id/seq/fold or id/seq
+        let (fold, seq) = if leading_folds > 0 {
+            body.split_at(leading_folds)
+        } else {
+            let (seq, fold) = body.split_at(body.len() - trailing_folds);
+            (fold, seq)
+        };
+
+        let Some(&(seq_line, _)) = seq.first() else {
+            return Err(self.error(header_line, Some(id), ParseErrorKind::MissingSequence));
+        };
+        if seq.iter().any(|(_, line)| structure::looks_like_dot_bracket(line)) {
+            return Err(self.error(header_line, Some(id), ParseErrorKind::AmbiguousLayout));
+        }
+
+        Ok(FastaRecord {
+            id: id.to_string(),
+            line: header_line,
+            seq_line,
+            fold_line: fold.first().map(|&(line, _)| line),
+            exp_fold: fold.iter().map(|(_, line)| line.as_str()).collect(),
+            seq: seq.iter().map(|(_, line)| line.as_str()).collect(),
+        })
+    }
+
+    // Next record as raw text, before the sequence is decoded.
+    pub fn next_record(&mut self) -> Option<Result<FastaRecord, ParseError>> {
+        let (header_line, id) = match self.next_header.take() {
+            Some(header) => header,
+            None => match self.next_line()? {
+                Ok((line_no, line)) => match line.strip_prefix('>') {
+                    Some(id) => (line_no, id.trim().to_string()),
+                    None => return Some(Err(self.error(line_no, None, ParseErrorKind::MissingHeader))),
+                },
+                Err(e) => return Some(Err(e)),
+            },
+        };
+
+        let mut body: Vec<(usize, String)> = Vec::new();
+        while let Some(next) = self.next_line() {
+            match next {
+                Ok((line_no, line)) => {
+                    if let Some(next_id) = line.strip_prefix('>') {
+                        self.next_header = Some((line_no, next_id.trim().to_string()));
+                        break;
+                    }
+                    body.push((line_no, line));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        Some(self.build_record(header_line, &id, body))
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = Result<RnaSequence, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = match self.next_record()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        let seq: String = if self.plain { record.seq.chars().map(plain_base).collect() } else { record.seq.clone() };
+        let mut rna_seq = match RnaSequence::try_new(&record.id, &record.exp_fold, &seq) {
+            Ok(rna_seq) => rna_seq,
+            Err(e) => {
+                let kind = ParseErrorKind::InvalidSequence(e.to_string());
+                return Some(Err(self.error(record.seq_line, Some(&record.id), kind)));
+            }
+        };
+        // the record is kept, its structure error points at the structure line
+        if let (Some(invalid), Some(fold_line)) = (&mut rna_seq.invalid_fold, record.fold_line) {
+            let kind = ParseErrorKind::InvalidStructure(invalid.error.clone());
+            invalid.error = self.error(fold_line, Some(&record.id), kind).to_string();
+        }
+        Some(Ok(rna_seq))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    Abort,
+    Skip,
+}
+
+#[derive(Debug, Default)]
+pub struct ParseReport {
+    pub sequences: Vec<RnaSequence>,
+    pub skipped: Vec<ParseError>,
+}
+
+// Reads up to `max` valid records. With OnError::Skip, invalid records are
+// collected in `skipped` instead of stopping the read; I/O errors always abort.
+pub fn read_fasta(filepath: &str, max: usize, on_error: OnError) -> Result<ParseReport, ParseError> {
+    let mut report = ParseReport::default();
+    for record in FastaReader::open(filepath)? {
+        if report.sequences.len() >= max {
+            break;
+        }
+        match record {
+            Ok(rna_seq) => report.sequences.push(rna_seq),
+            Err(e) if on_error == OnError::Skip && !e.is_fatal() => report.skipped.push(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(report)
+}
+
+// Valid records only, invalid ones are dropped silently: use `read_fasta` to
+// see what was skipped.
+pub fn parse_fasta(filepath: &str, max: usize) -> Result<Vec<RnaSequence>, ParseError> {
+    Ok(read_fasta(filepath, max, OnError::Skip)?.sequences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // A file in the temporary directory, removed when dropped.
+    pub(super) struct TempFile(PathBuf);
+
+    impl TempFile {
+        pub(super) fn new(name: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("rnaligner_{}_{}", std::process::id(), name)))
+        }
+
+        pub(super) fn with_content(name: &str, content: &str) -> Self {
+            let file = Self::new(name);
+            std::fs::write(&file.0, content).unwrap();
+            file
+        }
+
+        pub(super) fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    // A directory in the temporary directory, removed with its content when
+    // dropped.
+    pub(super) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(super) fn new(name: &str) -> Self {
+            let dir = TempDir(std::env::temp_dir().join(format!("rnaligner_{}_{}", std::process::id(), name)));
+            std::fs::create_dir_all(&dir.0).unwrap();
+            dir
+        }
+
+        // Writes `relative` below the directory, creating its parents.
+        pub(super) fn file(&self, relative: &str, content: &str) -> &Self {
+            let path = self.0.join(relative);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+            self
+        }
+
+        pub(super) fn join(&self, relative: &str) -> PathBuf {
+            self.0.join(relative)
+        }
+
+        pub(super) fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn read(content: &str) -> Vec<Result<RnaSequence, ParseError>> {
+        FastaReader::new(content.as_bytes(), "test.fasta").collect()
+    }
+
+    #[test]
+    fn errors_carry_their_line() {
+        let records = read("GGAAACC\n>bad\n\n((...))\nGGA@ACC\n>good\n((...))\nGGAAACC\n");
+        assert_eq!(records.len(), 3);
+        let missing = records[0].as_ref().unwrap_err();
+        assert!(matches!(missing.kind, ParseErrorKind::MissingHeader));
+        assert_eq!(missing.line, 1);
+        let invalid = records[1].as_ref().unwrap_err();
+        assert!(matches!(invalid.kind, ParseErrorKind::InvalidSequence(_)));
+        assert_eq!((invalid.line, invalid.id.as_deref()), (5, Some("bad")));
+        assert_eq!(records[2].as_ref().unwrap().id, "good");
+    }
+
+    #[test]
+    fn layouts_are_detected_per_record() {
+        let records = read(">fold_first\n((...))\nGGAAACC\n>seq_first\nGGAAACC\n((...))\n>plain\nGGAAA\nCC\n>pk_letters\nGGAAACC\n((.A)a)\n");
+        let folds: Vec<(&str, String, String)> = records
+            .iter()
+            .map(|record| record.as_ref().unwrap())
+            .map(|rna_seq| (rna_seq.id.as_str(), rna_seq.to_string(), rna_seq.exp_fold().unwrap()))
+            .collect();
+        assert_eq!(folds, [
+            ("fold_first", "GGAAACC".to_string(), "((...))".to_string()),
+            ("seq_first", "GGAAACC".to_string(), "((...))".to_string()),
+            ("plain", "GGAAACC".to_string(), String::new()),
+            ("pk_letters", "GGAAACC".to_string(), "((.[)])".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn unclear_layouts_are_errors() {
+        let records = read(">only_fold\n((...))\n>interleaved\n((...\nGGAAACC\n))\nGG\n>ok\nACGU\n");
+        let only_fold = records[0].as_ref().unwrap_err();
+        assert!(matches!(only_fold.kind, ParseErrorKind::MissingSequence));
+        assert_eq!((only_fold.line, only_fold.id.as_deref()), (1, Some("only_fold")));
+        let interleaved = records[1].as_ref().unwrap_err();
+        assert!(matches!(interleaved.kind, ParseErrorKind::AmbiguousLayout));
+        assert_eq!(interleaved.line, 3);
+        assert_eq!(records[2].as_ref().unwrap().id, "ok");
+    }
+
+    #[test]
+    fn unreadable_files_are_errors() {
+        let missing = "no/such/file.fasta";
+        assert!(read_fasta(missing, usize::MAX, OnError::Skip).is_err_and(|e| e.is_fatal()));
+        assert!(parse_fasta(missing, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn pseudoknotted_references_are_kept() {
+        let records = read(">pk\n((..[[..))..]]\nGGAACCAACCAAGG\n>brackets\n[[...]]\nGGAAACC\n");
+        let pk = records[0].as_ref().unwrap();
+        assert!(pk.has_pseudoknot());
+        assert_eq!(pk.exp_fold().unwrap(), "((..[[..))..]]");
+        assert_eq!(pk.nested().exp_fold().unwrap(), "((......))....");
+        // square brackets alone do not cross anything
+        let brackets = records[1].as_ref().unwrap();
+        assert!(!brackets.has_pseudoknot());
+        assert_eq!(brackets.exp_fold().unwrap(), "((...))");
+        assert_eq!(brackets.structure, brackets.nested().structure);
+        // an unclosed pseudoknot bracket is kept for validation to flag
+        let unclosed = read(">pk\n((..[[..))..].\nGGAACCAACCAAGG\n").remove(0).unwrap();
+        assert!(unclosed.structure.is_none() && unclosed.invalid_fold.is_some());
+        assert!(!unclosed.has_pseudoknot());
+    }
+
+    #[test]
+    fn modified_nucleotides_fold_as_their_parent() {
+        let rna_seq = RnaSequence::try_new("mod", "", "GCP7TDn.a").unwrap();
+        assert_eq!(rna_seq.to_string(), "GCUGUUNNA");
+        assert_eq!(rna_seq.modifications, vec![None, None, Some('P'), Some('7'), Some('T'), Some('D'), None, None, None]);
+        assert_eq!(rna_seq.to_modified_string(), "GCP7TDNNA");
+        assert!(RnaSequence::try_new("bad", "", "GC@A").is_err_and(|e| e.to_string().contains('@')));
+    }
+
+    #[test]
+    fn plain_sequences_have_no_modifications() {
+        let content = ">dna\nGCTtYRA\n";
+        let trnadb = read(content).remove(0).unwrap();
+        assert_eq!(trnadb.modifications[2..4], [Some('T'), Some('T')]);
+        let plain = FastaReader::new(content.as_bytes(), "test.fasta").plain().next().unwrap().unwrap();
+        assert_eq!(plain.to_string(), "GCUUNNA");
+        assert!(!plain.is_modified());
+    }
+
+    #[test]
+    fn invalid_structures_point_at_their_line() {
+        let records = read(">ok\n((...))\nGGAAACC\n\n>unbalanced\n((...)\nGGAAAC\n");
+        assert_eq!(records.len(), 2);
+        let unbalanced = records[1].as_ref().unwrap();
+        assert_eq!(unbalanced.exp_fold().unwrap(), "((...)");
+        let error = &unbalanced.invalid_fold.as_ref().unwrap().error;
+        assert!(error.starts_with("test.fasta:6: unbalanced: invalid structure"), "{}", error);
+    }
+}
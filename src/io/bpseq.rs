@@ -0,0 +1,145 @@
+use super::{ParseError, ParseErrorKind, RnaSequence, plain_base};
+use crate::compare::Score;
+use crate::structure::SecondaryStructure;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+fn bpseq_error(filepath: &str, line: usize, id: Option<&str>, kind: ParseErrorKind) -> ParseError {
+    ParseError {
+        source_name: filepath.to_string(),
+        line,
+        id: id.map(String::from),
+        kind,
+    }
+}
+
+// Name given in the header (`#Name:` for bpRNA, `Filename:` for CRW), without
+// extension.
+fn header_name(line: &str) -> Option<&str> {
+    let line = line.trim_start_matches('#').trim();
+    let (key, value) = line.split_once(':')?;
+    if !(key.eq_ignore_ascii_case("name") || key.eq_ignore_ascii_case("filename")) {
+        return None;
+    }
+    let value = value.trim();
+    Some(value.strip_suffix(".bpseq").unwrap_or(value))
+}
+
+// Reads the single structure of a BPSEQ file. Header lines before the first
+// base are allowed, the id is taken from them or from the file name.
+pub fn read_bpseq(filepath: &str) -> Result<RnaSequence, ParseError> {
+    let content = fs::read_to_string(filepath)
+        .map_err(|e| bpseq_error(filepath, 0, None, ParseErrorKind::Io(e)))?;
+
+    let mut id: Option<String> = None;
+    let mut seq = String::new();
+    let mut partners: Vec<(usize, usize)> = Vec::new(); // (line, partner)
+    for (line_no, line) in content.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let Ok(index) = columns[0].parse::<usize>() else {
+            if seq.is_empty() {
+                if let (None, Some(name)) = (&id, header_name(line)) {
+                    id = Some(name.to_string());
+                }
+                continue;
+            }
+            let kind = ParseErrorKind::InvalidLine(format!("expected a base line, found '{}'", line));
+            return Err(bpseq_error(filepath, line_no, id.as_deref(), kind));
+        };
+        if columns.len() != 3 || index != partners.len() + 1 {
+            let kind = ParseErrorKind::InvalidLine(format!("expected '{} <base> <partner>', found '{}'", partners.len() + 1, line));
+            return Err(bpseq_error(filepath, line_no, id.as_deref(), kind));
+        }
+        let partner = columns[2].parse::<usize>().map_err(|_| {
+            let kind = ParseErrorKind::InvalidLine(format!("invalid pair partner '{}'", columns[2]));
+            bpseq_error(filepath, line_no, id.as_deref(), kind)
+        })?;
+        seq.extend(columns[1].chars().map(plain_base));
+        partners.push((line_no, partner));
+    }
+
+    let id = id.unwrap_or_else(|| {
+        Path::new(filepath)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    let id = id.as_str();
+
+    for (i, &(line_no, partner)) in partners.iter().enumerate() {
+        if partner > partners.len() || partner == i + 1 {
+            let kind = ParseErrorKind::InvalidLine(format!("invalid pair partner {}", partner));
+            return Err(bpseq_error(filepath, line_no, Some(id), kind));
+        }
+    }
+
+    let partners: Vec<usize> = partners.into_iter().map(|(_, partner)| partner).collect();
+    let structure = SecondaryStructure::from_partners(&partners)
+        .map_err(|e| bpseq_error(filepath, 0, Some(id), ParseErrorKind::InvalidLine(e.to_string())))?;
+    RnaSequence::with_structure(id, Some(structure), &seq)
+        .map_err(|e| bpseq_error(filepath, 0, Some(id), ParseErrorKind::InvalidSequence(e.to_string())))
+}
+
+fn write_record(filepath: &str, id: &str, seq: &str, structure: &SecondaryStructure) -> Result<(), Box<dyn std::error::Error>> {
+    if structure.len() != seq.chars().count() {
+        return Err(format!("{}: structure has {} positions for {} bases", id, structure.len(), seq.chars().count()).into());
+    }
+
+    let mut out = BufWriter::new(File::create(filepath)?);
+    writeln!(out, "#Name: {}", id)?;
+    writeln!(out, "#Length: {}", structure.len())?;
+    for (i, base) in seq.chars().enumerate() {
+        writeln!(out, "{} {} {}", i + 1, base, structure.partner(i).map_or(0, |j| j + 1))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Writes a predicted structure as BPSEQ.
+pub fn write_bpseq(filepath: &str, score: &Score) -> Result<(), Box<dyn std::error::Error>> {
+    write_record(filepath, score.get_id()?, score.get_seq()?, score.get_structure()?)
+}
+
+// Writes the experimental structure of a sequence as BPSEQ.
+pub fn write_bpseq_reference(filepath: &str, rna_seq: &RnaSequence) -> Result<(), Box<dyn std::error::Error>> {
+    write_record(filepath, &rna_seq.id, &rna_seq.to_string(), rna_seq.exp_structure()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::tests::TempFile;
+
+    #[test]
+    fn round_trip() {
+        let file = TempFile::new("round_trip.bpseq");
+        let rna_seq = RnaSequence::new("bpRNA_1", "((..[[..))..]]", "GGAACCAACCAAGG");
+        write_bpseq_reference(file.path(), &rna_seq).unwrap();
+
+        let read = read_bpseq(file.path()).unwrap();
+        assert_eq!(read.id, rna_seq.id);
+        assert_eq!(read.to_string(), rna_seq.to_string());
+        assert_eq!(read.structure, rna_seq.structure);
+    }
+
+    #[test]
+    fn ambiguity_codes_are_unknown_bases() {
+        let file = TempFile::with_content("iupac.bpseq", "Filename: crw_1.bpseq\n1 G 8\n2 Y 0\n3 R 0\n4 K 0\n5 M 0\n6 S 0\n7 W 0\n8 C 1\n");
+        let read = read_bpseq(file.path()).unwrap();
+        assert_eq!(read.id, "crw_1");
+        assert_eq!(read.to_string(), "GNNNNNNC");
+        assert!(!read.is_modified());
+        assert_eq!(read.exp_fold().unwrap(), "(......)");
+    }
+
+    #[test]
+    fn misnumbered_lines_are_reported() {
+        let file = TempFile::with_content("invalid.bpseq", "#Name: bad\n1 G 0\n3 C 0\n");
+        let e = read_bpseq(file.path()).unwrap_err();
+        assert_eq!((e.line, e.id.as_deref()), (3, Some("bad")));
+    }
+}
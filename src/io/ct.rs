@@ -0,0 +1,162 @@
+use super::{ParseError, ParseErrorKind, RnaSequence, plain_base};
+use crate::compare::Score;
+use crate::structure::SecondaryStructure;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+fn ct_error(filepath: &str, line: usize, id: Option<&str>, kind: ParseErrorKind) -> ParseError {
+    ParseError {
+        source_name: filepath.to_string(),
+        line,
+        id: id.map(String::from),
+        kind,
+    }
+}
+
+// Drops the `ENERGY = x` / `dG = x` prefix that folding programs put in titles.
+fn strip_energy(title: &str) -> String {
+    let tokens: Vec<&str> = title.split_whitespace().collect();
+    match tokens.as_slice() {
+        [label, "=", _, rest @ ..] if label.eq_ignore_ascii_case("energy") || label.eq_ignore_ascii_case("dg") => {
+            rest.join(" ")
+        }
+        _ => title.trim().to_string(),
+    }
+}
+
+// Reads every structure of a connectivity table file. The record id is the
+// title of the header line, or the file name when the title is empty.
+// Pseudoknotted pairs are kept in the structure.
+pub fn read_ct(filepath: &str) -> Result<Vec<RnaSequence>, ParseError> {
+    let content = fs::read_to_string(filepath)
+        .map_err(|e| ct_error(filepath, 0, None, ParseErrorKind::Io(e)))?;
+    let invalid = |line: usize, id: Option<&str>, msg: String| {
+        ct_error(filepath, line, id, ParseErrorKind::InvalidLine(msg))
+    };
+
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let mut sequences: Vec<RnaSequence> = Vec::new();
+    while let Some((header_line, header)) = lines.next() {
+        let (len, title) = header.split_once(char::is_whitespace).unwrap_or((header, ""));
+        let len: usize = len
+            .parse()
+            .map_err(|_| invalid(header_line, None, format!("expected the sequence length, found '{}'", len)))?;
+        let mut id = strip_energy(title);
+        if id.is_empty() {
+            id = Path::new(filepath)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+        }
+        let id = id.as_str();
+
+        let mut seq = String::with_capacity(len);
+        let mut partners: Vec<usize> = vec![0; len];
+        for (i, partner_slot) in partners.iter_mut().enumerate() {
+            let (line_no, line) = lines
+                .next()
+                .ok_or_else(|| invalid(header_line, Some(id), format!("expected {} bases, found {}", len, i)))?;
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 5 {
+                return Err(invalid(line_no, Some(id), format!("expected at least 5 columns, found {}", columns.len())));
+            }
+            if columns[0].parse::<usize>() != Ok(i + 1) {
+                return Err(invalid(line_no, Some(id), format!("expected base number {}, found '{}'", i + 1, columns[0])));
+            }
+            let partner: usize = columns[4]
+                .parse()
+                .map_err(|_| invalid(line_no, Some(id), format!("invalid pair partner '{}'", columns[4])))?;
+            if partner > len || partner == i + 1 {
+                return Err(invalid(line_no, Some(id), format!("invalid pair partner {}", partner)));
+            }
+            seq.extend(columns[1].chars().map(plain_base));
+            *partner_slot = partner;
+        }
+
+        let structure = SecondaryStructure::from_partners(&partners)
+            .map_err(|e| invalid(header_line, Some(id), e.to_string()))?;
+        let rna_seq = RnaSequence::with_structure(id, Some(structure), &seq)
+            .map_err(|e| ct_error(filepath, header_line, Some(id), ParseErrorKind::InvalidSequence(e.to_string())))?;
+        sequences.push(rna_seq);
+    }
+
+    Ok(sequences)
+}
+
+// Writes predictions as a connectivity table, one structure per score.
+pub fn write_ct(filepath: &str, scores: &[Score]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut out = BufWriter::new(File::create(filepath)?);
+    for score in scores {
+        let seq = score.get_seq()?;
+        let structure = score.get_structure()?;
+        writeln!(out, "{}\t{}", seq.len(), score.get_id()?)?;
+        for (i, base) in seq.chars().enumerate() {
+            let partner = structure.partner(i).map_or(0, |j| j + 1);
+            writeln!(out, "{}\t{}\t{}\t{}\t{}\t{}", i + 1, base, i, if i + 1 < seq.len() { i + 2 } else { 0 }, partner, i + 1)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folder::Folder;
+    use crate::io::tests::TempFile;
+
+    // Predicts a fixed structure, to write it through a Score.
+    struct Fixed(SecondaryStructure);
+
+    impl Folder for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn fold(&self, _: &RnaSequence) -> Result<SecondaryStructure, Box<dyn std::error::Error>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = TempFile::new("round_trip.ct");
+        // pseudoknotted
+        let structure = SecondaryStructure::from_dot_bracket("((..[[..))..]]").unwrap();
+        let rna_seq = RnaSequence::new("tRNA 1", "", "GGAACCAACCAAGG");
+        let score = Score::with_folder(&rna_seq, &Fixed(structure.clone())).unwrap();
+        write_ct(file.path(), &[score.clone(), score]).unwrap();
+
+        let read = read_ct(file.path()).unwrap();
+        assert_eq!(read.len(), 2);
+        for read in read {
+            assert_eq!(read.id, "tRNA 1");
+            assert_eq!(read.to_string(), "GGAACCAACCAAGG");
+            assert_eq!(read.structure.as_ref(), Some(&structure));
+        }
+    }
+
+    #[test]
+    fn ambiguity_codes_are_unknown_bases() {
+        let rows: String = "GYRKMSWC".chars().enumerate().map(|(i, base)| format!("{} {} {} {} 0 {}\n", i + 1, base, i, i + 2, i + 1)).collect();
+        let file = TempFile::with_content("iupac.ct", &format!("8 ENERGY = -1.2 iupac\n{}", rows));
+        let read = read_ct(file.path()).unwrap();
+        assert_eq!(read[0].id, "iupac");
+        assert_eq!(read[0].to_string(), "GNNNNNNC");
+        assert!(!read[0].is_modified());
+    }
+
+    #[test]
+    fn invalid_partners_are_reported_with_their_line() {
+        let file = TempFile::with_content("invalid.ct", "3 bad\n1 G 0 2 3 1\n2 A 1 3 0 2\n3 C 2 0 9 3\n");
+        let e = read_ct(file.path()).unwrap_err();
+        assert_eq!((e.line, e.id.as_deref()), (4, Some("bad")));
+        assert!(e.to_string().contains("invalid pair partner 9"));
+    }
+}
@@ -0,0 +1,209 @@
+use super::{FastaReader, OnError, ParseError, ParseErrorKind, RnaSequence, read_bpseq, read_ct, read_stockholm};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct DatasetEntry {
+    pub family: String,
+    pub path: PathBuf,
+    pub sequence: RnaSequence,
+}
+
+#[derive(Debug, Default)]
+pub struct Dataset {
+    pub entries: Vec<DatasetEntry>,
+    pub skipped: Vec<ParseError>,
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> ParseError {
+    ParseError {
+        source_name: path.display().to_string(),
+        line: 0,
+        id: None,
+        kind: ParseErrorKind::Io(e),
+    }
+}
+
+// bpRNA `.dbn` files use `#Name:` instead of a `>` header. Comment lines are
+// blanked rather than removed so line numbers stay right.
+fn read_dbn(filepath: &str) -> Result<Vec<Result<RnaSequence, ParseError>>, ParseError> {
+    let content = fs::read_to_string(filepath).map_err(|e| io_error(Path::new(filepath), e))?;
+    let content: String = content
+        .lines()
+        .map(|line| match line.trim().strip_prefix("#Name:") {
+            Some(name) => format!(">{}\n", name.trim()),
+            None if line.starts_with('#') => String::from("\n"),
+            None => format!("{}\n", line),
+        })
+        .collect();
+    Ok(FastaReader::new(content.as_bytes(), filepath).plain().collect())
+}
+
+// Records of one file, each with the family it declares (Stockholm only).
+type FileRecords = Vec<(Result<RnaSequence, ParseError>, Option<String>)>;
+
+fn read_file(path: &Path) -> Result<Option<FileRecords>, ParseError> {
+    let filepath = path.to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let records: FileRecords = match extension.as_str() {
+        "ct" => read_ct(&filepath)?.into_iter().map(|s| (Ok(s), None)).collect(),
+        "bpseq" => vec![(Ok(read_bpseq(&filepath)?), None)],
+        "dbn" | "db" => read_dbn(&filepath)?.into_iter().map(|r| (r, None)).collect(),
+        "fasta" | "fa" | "fna" => FastaReader::open(&filepath)?.plain().map(|r| (r, None)).collect(),
+        "sto" | "stk" | "stockholm" => read_stockholm(&filepath)?
+            .into_iter()
+            .flat_map(|alignment| {
+                let family = alignment.id;
+                alignment.sequences.into_iter().map(move |s| (Ok(s), Some(family.clone())))
+            })
+            .collect(),
+        _ => return Ok(None),
+    };
+    Ok(Some(records))
+}
+
+// Family of a file: the first directory below the dataset root
+// (RNAStrAlign), else the file name up to the first '_' (ArchiveII).
+fn infer_family(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut components = relative.components();
+    if let (Some(first), Some(_)) = (components.next(), components.next()) {
+        return first.as_os_str().to_string_lossy().to_string();
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match stem.split_once('_') {
+        Some((prefix, _)) if !prefix.is_empty() => prefix.to_string(),
+        _ => stem,
+    }
+}
+
+// Files below `dir`, sorted. Symbolic links to directories are not followed,
+// as one pointing to a parent would be walked forever.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ParseError> {
+    let mut entries: Vec<(PathBuf, fs::FileType)> = fs::read_dir(dir)
+        .map_err(|e| io_error(dir, e))?
+        .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))))
+        .collect::<Result<_, _>>()
+        .map_err(|e| io_error(dir, e))?;
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, file_type) in entries {
+        if file_type.is_dir() {
+            collect_files(&path, files)?;
+        } else if !path.is_dir() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Dataset {
+    // Loads every .ct, .bpseq, .dbn, .fasta and Stockholm file below `dirpath`.
+    // Files with other extensions are ignored.
+    pub fn load_dir(dirpath: &str, on_error: OnError) -> Result<Self, ParseError> {
+        let root = Path::new(dirpath);
+        let mut files: Vec<PathBuf> = Vec::new();
+        collect_files(root, &mut files)?;
+
+        let mut dataset = Dataset::default();
+        for path in files {
+            let records = match read_file(&path) {
+                Ok(Some(records)) => records,
+                Ok(None) => continue,
+                Err(e) if on_error == OnError::Skip && !e.is_fatal() => {
+                    dataset.skipped.push(e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for (record, family) in records {
+                match record {
+                    Ok(sequence) => dataset.entries.push(DatasetEntry {
+                        family: family.unwrap_or_else(|| infer_family(root, &path)),
+                        path: path.clone(),
+                        sequence,
+                    }),
+                    Err(e) if on_error == OnError::Skip && !e.is_fatal() => dataset.skipped.push(e),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(dataset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn sequences(&self) -> Vec<RnaSequence> {
+        self.entries.iter().map(|entry| entry.sequence.clone()).collect()
+    }
+
+    pub fn families(&self) -> Vec<String> {
+        self.by_family().into_keys().collect()
+    }
+
+    pub fn by_family(&self) -> BTreeMap<String, Vec<RnaSequence>> {
+        let mut families: BTreeMap<String, Vec<RnaSequence>> = BTreeMap::new();
+        for entry in &self.entries {
+            families.entry(entry.family.clone()).or_default().push(entry.sequence.clone());
+        }
+        families
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::tests::TempDir;
+
+    #[test]
+    fn families_from_directories_and_file_names() {
+        let dir = TempDir::new("dataset_families");
+        dir.file("RNaseP/a.dbn", "#Name: a\nGGGAAACCC\n(((...)))\n")
+            .file("RNaseP/b.bpseq", "1 G 5\n2 A 0\n3 A 0\n4 A 0\n5 C 1\n")
+            .file("5s_one.fasta", ">one\n((....))\nGGAAAACC\n")
+            .file("align.sto", "# STOCKHOLM 1.0\n#=GF ID RF00005\nx GGAAACC\n#=GC SS_cons ((...))\n//\n")
+            .file("notes.txt", "not a dataset file\n");
+        let dataset = Dataset::load_dir(dir.path(), OnError::Abort).unwrap();
+
+        assert_eq!(dataset.len(), 4);
+        assert_eq!(dataset.families(), ["5s", "RF00005", "RNaseP"]);
+        let by_family = dataset.by_family();
+        assert_eq!(by_family["RNaseP"].iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(by_family["5s"][0].exp_fold().unwrap(), "((....))");
+    }
+
+    #[test]
+    fn unreadable_records_are_skipped_or_abort() {
+        let dir = TempDir::new("dataset_errors");
+        dir.file("good.fasta", ">good\nGGAAACC\n").file("bad.fasta", ">bad\n((...))\n");
+        let dataset = Dataset::load_dir(dir.path(), OnError::Skip).unwrap();
+        assert_eq!(dataset.sequences().iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ["good"]);
+        assert_eq!(dataset.skipped.len(), 1);
+        assert!(matches!(dataset.skipped[0].kind, ParseErrorKind::MissingSequence));
+        assert!(Dataset::load_dir(dir.path(), OnError::Abort).is_err());
+        assert!(Dataset::load_dir("no/such/dir", OnError::Skip).is_err_and(|e| e.is_fatal()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn linked_directories_are_not_followed() {
+        let dir = TempDir::new("dataset_link");
+        dir.file("family/a.fasta", ">a\nGGAAACC\n");
+        std::os::unix::fs::symlink(dir.path(), dir.join("family/loop")).unwrap();
+        let dataset = Dataset::load_dir(dir.path(), OnError::Abort).unwrap();
+        assert_eq!(dataset.len(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+use super::{FastaReader, ParseError, ParseErrorKind, RnaSequence};
+use crate::structure::SecondaryStructure;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+#[derive(Debug, Default)]
+pub struct JoinReport {
+    pub sequences: Vec<RnaSequence>,
+    pub only_in_sequences: Vec<String>,
+    pub only_in_structures: Vec<String>,
+    pub length_mismatches: Vec<(String, usize, usize)>, // id, sequence length, structure length
+    pub skipped: Vec<ParseError>,
+}
+
+// Takes the `_` gap columns of an aligned sequence out of its structure, with
+// the pairs they are in. Structures that do not line up with the gapped
+// sequence are left as they are, for the length check to report.
+fn strip_gaps(seq: &str, fold: &str) -> String {
+    let columns: Vec<char> = seq.chars().filter(|c| !c.is_whitespace()).collect();
+    if !columns.contains(&'_') || columns.len() != fold.chars().count() {
+        return fold.to_string();
+    }
+    let Ok(structure) = SecondaryStructure::from_dot_bracket(fold) else {
+        return fold.to_string();
+    };
+
+    let mut position: Vec<Option<usize>> = Vec::with_capacity(columns.len());
+    let mut len = 0;
+    for &c in &columns {
+        if c == '_' {
+            position.push(None);
+        } else {
+            position.push(Some(len));
+            len += 1;
+        }
+    }
+    let pairs: Vec<(usize, usize)> = structure.pairs().filter_map(|(i, j)| position[i].zip(position[j])).collect();
+    SecondaryStructure::from_pairs(len, &pairs)
+        .and_then(|structure| structure.to_dot_bracket())
+        .unwrap_or_else(|_| fold.to_string())
+}
+
+// Joins a FASTA file of sequences with a file of structures by record id. The
+// sequence always comes from `seq_path` and the structure from `fold_path`,
+// records are kept in the order of `seq_path`.
+pub fn join_datasets(seq_path: &str, fold_path: &str) -> Result<JoinReport, ParseError> {
+    let mut report = JoinReport::default();
+
+    let mut folds: HashMap<String, (usize, String)> = HashMap::new(); // structure line, structure
+    let mut fold_ids: Vec<String> = Vec::new();
+    let mut reader = FastaReader::open(fold_path)?;
+    while let Some(record) = reader.next_record() {
+        match record {
+            Ok(record) if record.exp_fold.is_empty() => {}
+            Ok(record) => {
+                if let Entry::Vacant(entry) = folds.entry(record.id.clone()) {
+                    fold_ids.push(record.id);
+                    entry.insert((record.fold_line.unwrap_or(record.line), record.exp_fold));
+                }
+            }
+            Err(e) if !e.is_fatal() => report.skipped.push(e),
+            Err(e) => return Err(e),
+        }
+    }
+
+    let mut joined: HashSet<String> = HashSet::new();
+    let mut reader = FastaReader::open(seq_path)?;
+    while let Some(record) = reader.next_record() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) if !e.is_fatal() => {
+                report.skipped.push(e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if !joined.insert(record.id.clone()) {
+            continue;
+        }
+        let Some((fold_line, fold)) = folds.get(&record.id) else {
+            report.only_in_sequences.push(record.id);
+            continue;
+        };
+        let fold = strip_gaps(&record.seq, fold);
+        match RnaSequence::try_new(&record.id, &fold, &record.seq) {
+            Ok(rna_seq) if rna_seq.sizeof() != fold.chars().count() => {
+                report.length_mismatches.push((record.id, rna_seq.sizeof(), fold.chars().count()));
+            }
+            Ok(mut rna_seq) => {
+                if let Some(invalid) = &mut rna_seq.invalid_fold {
+                    let error = ParseError {
+                        source_name: fold_path.to_string(),
+                        line: *fold_line,
+                        id: Some(record.id),
+                        kind: ParseErrorKind::InvalidStructure(invalid.error.clone()),
+                    };
+                    invalid.error = error.to_string();
+                }
+                report.sequences.push(rna_seq);
+            }
+            Err(e) => report.skipped.push(ParseError {
+                source_name: seq_path.to_string(),
+                line: record.seq_line,
+                id: Some(record.id),
+                kind: ParseErrorKind::InvalidSequence(e.to_string()),
+            }),
+        }
+    }
+
+    report.only_in_structures = fold_ids
+        .into_iter()
+        .filter(|id| !joined.contains(id))
+        .collect();
+
+    Ok(report)
+}
+
+// Writes records as `>id`, structure, sequence lines, the layout read by
+// `parse_fasta`. Modified nucleotides are written with their tRNAdb code.
+pub fn write_dataset(filepath: &str, sequences: &[RnaSequence]) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(filepath)?);
+    for rna_seq in sequences {
+        writeln!(out, ">{}", rna_seq.id)?;
+        if rna_seq.has_structure() || rna_seq.invalid_fold.is_some() {
+            let fold = rna_seq.exp_fold().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+            writeln!(out, "{}", fold)?;
+        }
+        writeln!(out, "{}", rna_seq.to_modified_string())?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::tests::TempFile;
+    use crate::io::{OnError, read_fasta};
+
+    #[test]
+    fn join_by_id() {
+        let seqs = TempFile::with_content("join_seqs.fasta", ">gapped\nGGGGAAA_CCC\n>short\nACGU\n>no_fold\nAAAA\n");
+        let folds = TempFile::with_content(
+            "join_folds.fasta",
+            ">no_seq\n((...))\nGGAAACC\n>short\n((...))\nGGAAACC\n>gapped\n((((...))))\nGGGGAAAUCCC\n",
+        );
+        let report = join_datasets(seqs.path(), folds.path()).unwrap();
+
+        assert_eq!(report.sequences.len(), 1);
+        let gapped = &report.sequences[0];
+        assert_eq!((gapped.id.as_str(), gapped.to_string().as_str()), ("gapped", "GGGGAAACCC"));
+        // the gap took the pair it was in with it
+        assert_eq!(gapped.exp_fold().unwrap(), "(((....)))");
+        assert_eq!(report.only_in_sequences, ["no_fold"]);
+        assert_eq!(report.only_in_structures, ["no_seq"]);
+        assert_eq!(report.length_mismatches, [("short".to_string(), 4, 7)]);
+        assert!(report.skipped.is_empty());
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        let seqs = TempFile::with_content("join_missing.fasta", ">a\nACGU\n");
+        assert!(join_datasets(seqs.path(), "no/such/file.fasta").is_err_and(|e| e.is_fatal()));
+        assert!(join_datasets("no/such/file.fasta", seqs.path()).is_err_and(|e| e.is_fatal()));
+    }
+
+    #[test]
+    fn dot_bracket_round_trip() {
+        let file = TempFile::new("join_round_trip.fasta");
+        let sequences = vec![
+            RnaSequence::new("paired", "(((...)))..", "GGGAAAUCCAA"),
+            RnaSequence::new("pseudoknot", "((..[[..))..]]", "GGAACCAACCAAGG"),
+            RnaSequence::new("unknown", "", "ACGU"),
+            RnaSequence::new("modified", "(((....)))", "GGGAPAAUCC"),
+        ];
+        write_dataset(file.path(), &sequences).unwrap();
+
+        let report = read_fasta(file.path(), usize::MAX, OnError::Abort).unwrap();
+        assert_eq!(report.sequences.len(), sequences.len());
+        for (read, written) in report.sequences.iter().zip(&sequences) {
+            assert_eq!(read.id, written.id);
+            assert_eq!(read.to_modified_string(), written.to_modified_string());
+            assert_eq!(read.structure, written.structure);
+        }
+    }
+}
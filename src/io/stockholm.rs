@@ -0,0 +1,218 @@
+use super::{ParseError, ParseErrorKind, RnaSequence, plain_base};
+use crate::structure::SecondaryStructure;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct StockholmAlignment {
+    pub id: String,
+    pub accession: Option<String>,
+    pub sequences: Vec<RnaSequence>,
+}
+
+fn is_gap(c: char) -> bool {
+    matches!(c, '.' | '-' | '_' | '~')
+}
+
+#[derive(Default)]
+struct Block {
+    id: Option<String>,
+    accession: Option<String>,
+    names: Vec<String>,
+    aligned: HashMap<String, (usize, String)>, // first line, aligned sequence
+    structures: HashMap<String, String>,       // #=GR <name> SS
+    ss_cons: Option<(usize, String)>,
+}
+
+struct Parser<'a> {
+    filepath: &'a str,
+}
+
+impl Parser<'_> {
+    fn error(&self, line: usize, id: Option<&str>, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            source_name: self.filepath.to_string(),
+            line,
+            id: id.map(String::from),
+            kind,
+        }
+    }
+
+    fn invalid(&self, line: usize, id: Option<&str>, msg: String) -> ParseError {
+        self.error(line, id, ParseErrorKind::InvalidLine(msg))
+    }
+
+    // Projects the alignment structure on one member: gap columns are removed
+    // together with the pairs they took part in.
+    fn project(&self, name: &str, line: usize, aligned: &str, fold: &str) -> Result<RnaSequence, ParseError> {
+        let columns: Vec<char> = aligned.chars().collect();
+        let mut seq = String::with_capacity(columns.len());
+        let mut position: Vec<Option<usize>> = Vec::with_capacity(columns.len());
+        for &c in &columns {
+            if is_gap(c) {
+                position.push(None);
+            } else {
+                position.push(Some(seq.len()));
+                // degenerate IUPAC codes would be read as tRNAdb modifications
+                seq.push(plain_base(c));
+            }
+        }
+
+        let mut structure = None;
+        if !fold.is_empty() {
+            if fold.chars().count() != columns.len() {
+                let msg = format!("structure has {} columns, alignment has {}", fold.chars().count(), columns.len());
+                return Err(self.invalid(line, Some(name), msg));
+            }
+            let alignment_structure = SecondaryStructure::from_dot_bracket(fold)
+                .map_err(|e| self.invalid(line, Some(name), e.to_string()))?;
+            let projected: Vec<(usize, usize)> = alignment_structure
+                .pairs()
+                .filter_map(|(i, j)| position[i].zip(position[j]))
+                .collect();
+            let projected = SecondaryStructure::from_pairs(seq.len(), &projected)
+                .map_err(|e| self.invalid(line, Some(name), e.to_string()))?;
+            structure = Some(projected);
+        }
+
+        RnaSequence::with_structure(name, structure, &seq)
+            .map_err(|e| self.error(line, Some(name), ParseErrorKind::InvalidSequence(e.to_string())))
+    }
+
+    fn finish(&self, block: Block, index: usize) -> Result<StockholmAlignment, ParseError> {
+        let mut sequences = Vec::with_capacity(block.names.len());
+        for name in &block.names {
+            let (line, aligned) = &block.aligned[name];
+            let fold = match (block.structures.get(name), &block.ss_cons) {
+                (Some(fold), _) => fold.as_str(),
+                (None, Some((_, ss_cons))) => ss_cons.as_str(),
+                (None, None) => "",
+            };
+            sequences.push(self.project(name, *line, aligned, fold)?);
+        }
+
+        let id = block.id.unwrap_or_else(|| {
+            let stem = Path::new(self.filepath)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if index == 0 { stem } else { format!("{}_{}", stem, index + 1) }
+        });
+
+        Ok(StockholmAlignment { id, accession: block.accession, sequences })
+    }
+}
+
+// Reads every alignment of a Stockholm file. Each member gets its own `#=GR SS`
+// structure if present, otherwise the `#=GC SS_cons` consensus structure.
+pub fn read_stockholm(filepath: &str) -> Result<Vec<StockholmAlignment>, ParseError> {
+    let parser = Parser { filepath };
+    let content = fs::read_to_string(filepath)
+        .map_err(|e| parser.error(0, None, ParseErrorKind::Io(e)))?;
+
+    let mut alignments: Vec<StockholmAlignment> = Vec::new();
+    let mut block = Block::default();
+    let mut in_block = false;
+    for (line_no, line) in content.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+        if line.is_empty() || line.starts_with("# STOCKHOLM") {
+            continue;
+        }
+        if line == "//" {
+            alignments.push(parser.finish(std::mem::take(&mut block), alignments.len())?);
+            in_block = false;
+            continue;
+        }
+        in_block = true;
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["#=GF", "ID", id, ..] => block.id = Some(id.to_string()),
+            ["#=GF", "AC", accession, ..] => block.accession = Some(accession.to_string()),
+            ["#=GC", "SS_cons", fold] => {
+                block.ss_cons.get_or_insert((line_no, String::new())).1.push_str(fold);
+            }
+            ["#=GR", name, "SS", fold] => {
+                block.structures.entry(name.to_string()).or_default().push_str(fold);
+            }
+            [tag, ..] if tag.starts_with('#') => {}
+            [name, aligned] => {
+                if !block.aligned.contains_key(*name) {
+                    block.names.push(name.to_string());
+                }
+                block.aligned.entry(name.to_string()).or_insert((line_no, String::new())).1.push_str(aligned);
+            }
+            _ => return Err(parser.invalid(line_no, None, format!("expected '<name> <aligned sequence>', found '{}'", line))),
+        }
+    }
+    if in_block {
+        alignments.push(parser.finish(block, alignments.len())?);
+    }
+
+    Ok(alignments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::tests::TempFile;
+
+    // Writes each structure into an alignment with gap columns inserted at
+    // `gaps`, as #=GR lines, and reads them back.
+    #[test]
+    fn round_trip() {
+        let members = [
+            ("first", "GGGAAAUCCAA", "(((...)))..", vec![3, 3, 9]),
+            ("second", "GGAACCAACCAAGG", "((..[[..))..]]", vec![0, 7]),
+        ];
+        let mut content = String::from("# STOCKHOLM 1.0\n#=GF ID RF00001\n");
+        for (name, seq, fold, gaps) in &members {
+            let with_gaps = |s: &str| {
+                let mut chars: Vec<char> = s.chars().collect();
+                for &gap in gaps.iter().rev() {
+                    chars.insert(gap, '-');
+                }
+                chars.into_iter().collect::<String>()
+            };
+            content.push_str(&format!("{} {}\n", name, with_gaps(seq)));
+            content.push_str(&format!("#=GR {} SS {}\n", name, with_gaps(fold).replace('-', ".")));
+        }
+        content.push_str("//\n");
+        let file = TempFile::with_content("round_trip.sto", &content);
+
+        let alignments = read_stockholm(file.path()).unwrap();
+        assert_eq!(alignments.len(), 1);
+        assert_eq!(alignments[0].id, "RF00001");
+        for (read, (name, seq, fold, _)) in alignments[0].sequences.iter().zip(&members) {
+            assert_eq!(read.id, *name);
+            assert_eq!(read.to_string(), *seq);
+            assert_eq!(read.exp_fold().unwrap(), *fold);
+        }
+    }
+
+    // The consensus pairs a column where the second member has a gap: that
+    // pair is dropped from its structure, the others are kept.
+    #[test]
+    fn gap_columns_take_their_pairs() {
+        let content = "# STOCKHOLM 1.0\nfirst  GGGAAAUCC\nsecond GG-AAAUCC\n#=GC SS_cons (((...)))\n//\n";
+        let file = TempFile::with_content("broken_pair.sto", content);
+        let alignments = read_stockholm(file.path()).unwrap();
+        let folds: Vec<(String, String)> = alignments[0]
+            .sequences
+            .iter()
+            .map(|rna_seq| (rna_seq.to_string(), rna_seq.exp_fold().unwrap()))
+            .collect();
+        assert_eq!(folds, [("GGGAAAUCC".to_string(), "(((...)))".to_string()), ("GGAAAUCC".to_string(), "((....))".to_string())]);
+        // no #=GF ID, named after the file
+        assert!(alignments[0].id.ends_with("broken_pair"), "{}", alignments[0].id);
+    }
+
+    #[test]
+    fn structures_of_another_width_are_reported() {
+        let content = "# STOCKHOLM 1.0\n\nfirst GGGAAAUCC\n#=GR first SS (((...))).\n//\n";
+        let file = TempFile::with_content("wide_structure.sto", content);
+        let e = read_stockholm(file.path()).unwrap_err();
+        assert_eq!((e.line, e.id.as_deref()), (3, Some("first")));
+        assert!(e.to_string().contains("structure has 10 columns, alignment has 9"), "{}", e);
+    }
+}
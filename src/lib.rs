@@ -1,5 +1,13 @@
 pub mod benchmark;
 pub mod compare;
+pub mod elements;
+pub mod folder;
 pub mod io;
+pub mod modifications;
 pub mod nussinov;
+pub mod profile;
+pub mod structure;
+pub mod trna;
+pub mod validation;
+pub mod vienna;
 
@@ -1,10 +1,71 @@
-use rnaligner::io::{RnaSequence, parse_fasta};
+use rnaligner::io::{OnError, RnaSequence, read_fasta, join_datasets, write_dataset};
 use rnaligner::compare::Score;
 use rnaligner::benchmark::Benchmark;
+use rnaligner::validation::{Policy, validate};
+use std::env;
+use std::process;
 // use std::mem;
 
+const USAGE: &str = "usage:
+    rnaligner                                   run the benchmark on data/
+    rnaligner join <fasta> <structures> <out>   join sequences and structures by id";
+
 fn main() {
-    let seq_list: Vec<RnaSequence> = parse_fasta("data/trna_unmodified_dot_bracket.txt", 395); // 395 max for nussinov on my machine
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        None => {
+            if let Err(e) = run_benchmark() {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        Some("join") if args.len() == 5 => join(&args[2], &args[3], &args[4]),
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+fn join(seq_path: &str, fold_path: &str, out_path: &str) {
+    let report = match join_datasets(seq_path, fold_path) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    for id in &report.only_in_sequences {
+        eprintln!("no structure for {} in {}", id, fold_path);
+    }
+    for id in &report.only_in_structures {
+        eprintln!("no sequence for {} in {}", id, seq_path);
+    }
+    for (id, seq_len, fold_len) in &report.length_mismatches {
+        eprintln!("length mismatch for {}: sequence {} nt, structure {} nt", id, seq_len, fold_len);
+    }
+    for e in &report.skipped {
+        eprintln!("skipped {}", e);
+    }
+
+    if let Err(e) = write_dataset(out_path, &report.sequences) {
+        eprintln!("error: failed to write {}: {}", out_path, e);
+        process::exit(1);
+    }
+    println!("Joined {} records into {}", report.sequences.len(), out_path);
+    println!("Only in {}: {}", seq_path, report.only_in_sequences.len());
+    println!("Only in {}: {}", fold_path, report.only_in_structures.len());
+    println!("Length mismatches: {}", report.length_mismatches.len());
+    println!("Skipped: {}", report.skipped.len());
+}
+
+fn run_benchmark() -> Result<(), Box<dyn std::error::Error>> {
+    let report = read_fasta("data/trna_unmodified_dot_bracket.txt", usize::MAX, OnError::Skip)?;
+    for e in &report.skipped {
+        eprintln!("skipped {}", e);
+    }
+    let seq_list: Vec<RnaSequence> = report.sequences;
 
     // let vec_size = mem::size_of::<Vec<RnaSequence>>(); 
     // let size = mem::size_of::<RnaSequence>();
@@ -15,6 +76,10 @@ fn main() {
 
     println!();
 
+    let (seq_list, report) = validate(seq_list, 3, Policy::Keep);
+    report.repr();
+    println!();
+
     let bench = Benchmark::new(seq_list);
     bench.repr();
     println!();
@@ -32,4 +97,5 @@ fn main() {
     let _ = nussinov_score.expect("error nussinov").repr();
     let _ = vienna_score.expect("error vienna").repr(); 
     println!();
+    Ok(())
 }
@@ -0,0 +1,128 @@
+/// A modified nucleoside as written in tRNAdb / MODOMICS one-letter sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modification {
+    pub code: char,
+    pub name: &'static str,
+    pub parent: char, // A, U, G, C or N when the parent is unknown
+}
+
+const fn m(code: char, name: &'static str, parent: char) -> Modification {
+    Modification { code, name, parent }
+}
+
+// One-letter codes used by tRNAdb for modified nucleosides, with the unmodified
+// base each one derives from. Plain A/C/G/U and the unknown N are not
+// listed here; T is, as ribothymidine, which it only means in tRNAdb sequences
+// (other formats read it as U).
+pub const MODIFICATIONS: &[Modification] = &[
+    // adenosine
+    m('"', "m1A", 'A'),
+    m('/', "m2A", 'A'),
+    m('=', "m6A", 'A'),
+    m('+', "i6A", 'A'),
+    m('*', "ms2i6A", 'A'),
+    m('`', "io6A", 'A'),
+    m('6', "t6A", 'A'),
+    m('E', "m6t6A", 'A'),
+    m('[', "ms2t6A", 'A'),
+    m(':', "Am", 'A'),
+    m('I', "I", 'A'),
+    m('O', "m1I", 'A'),
+    m('^', "Ar(p)", 'A'),
+    m('H', "xA", 'A'),
+    // cytidine
+    m('\'', "m3C", 'C'),
+    m('?', "m5C", 'C'),
+    m('B', "Cm", 'C'),
+    m('%', "s2C", 'C'),
+    m('M', "ac4C", 'C'),
+    m('>', "f5C", 'C'),
+    m('}', "k2C", 'C'),
+    m('<', "xC", 'C'),
+    // guanosine
+    m('K', "m1G", 'G'),
+    m('L', "m2G", 'G'),
+    m('7', "m7G", 'G'),
+    m('#', "Gm", 'G'),
+    m('R', "m22G", 'G'),
+    m('|', "m2Gm", 'G'),
+    m('Q', "Q", 'G'),
+    m('9', "galQ", 'G'),
+    m('8', "manQ", 'G'),
+    m('(', "G+", 'G'),
+    m('Y', "yW", 'G'),
+    m('W', "o2yW", 'G'),
+    m(';', "xG", 'G'),
+    // uridine
+    m('D', "D", 'U'),
+    m('P', "Y", 'U'),
+    m('J', "Um", 'U'),
+    m('Z', "Ym", 'U'),
+    m(']', "m1Y", 'U'),
+    m('2', "s2U", 'U'),
+    m('4', "s4U", 'U'),
+    m('5', "mo5U", 'U'),
+    m('V', "cmo5U", 'U'),
+    m('υ', "mcmo5U", 'U'),
+    m('X', "acp3U", 'U'),
+    m('1', "mcm5U", 'U'),
+    m('3', "mcm5s2U", 'U'),
+    m('{', "mnm5U", 'U'),
+    m('S', "mnm5s2U", 'U'),
+    m('!', "cmnm5U", 'U'),
+    m('$', "cmnm5s2U", 'U'),
+    m('&', "ncm5U", 'U'),
+    m('~', "ncm5Um", 'U'),
+    m('F', "m5s2U", 'U'),
+    m('\\', "m5Um", 'U'),
+    m('T', "m5U", 'U'),
+    // other codes of the tRNAdb export (data/dot_bracket.txt), named by their
+    // parent only, which is the base at the same position of the unmodified
+    // sequence
+    m('≠', "xA", 'A'),
+    m('e', "xA", 'A'),
+    m('ÿ', "xA", 'A'),
+    m('«', "xA", 'A'),
+    m('¹', "xA", 'A'),
+    m('’', "xC", 'C'),
+    m('°', "xC", 'C'),
+    m('¿', "xC", 'C'),
+    m('⊄', "xG", 'G'),
+    m(')', "xU", 'U'),
+    m('Ê', "xU", 'U'),
+    m('Ł', "xU", 'U'),
+    m('∃', "xU", 'U'),
+    m(',', "xU", 'U'),
+    m('Ð', "xU", 'U'),
+];
+
+pub fn lookup(code: char) -> Option<&'static Modification> {
+    MODIFICATIONS.iter().find(|modif| modif.code == code)
+}
+
+pub fn parent_base(code: char) -> Option<char> {
+    lookup(code).map(|modif| modif.parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_map_to_their_parent() {
+        assert_eq!(lookup('P').map(|modif| (modif.name, modif.parent)), Some(("Y", 'U')));
+        assert_eq!(parent_base('T'), Some('U'));
+        assert_eq!(parent_base('7'), Some('G'));
+        assert_eq!(parent_base('"'), Some('A'));
+        for plain in ['A', 'C', 'G', 'U', 'N', '.', '@'] {
+            assert_eq!(parent_base(plain), None, "{}", plain);
+        }
+    }
+
+    #[test]
+    fn codes_are_unique() {
+        for (i, modif) in MODIFICATIONS.iter().enumerate() {
+            assert!(MODIFICATIONS[i + 1..].iter().all(|other| other.code != modif.code), "{} listed twice", modif.code);
+        }
+    }
+}
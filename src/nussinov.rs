@@ -1,282 +1,453 @@
-pub struct Matrix {
-    seq_len: usize,
-    seq_str: String,
-    matrix: Vec<Vec<Node>>,
-}
+use crate::folder::Folder;
+use crate::io::RnaSequence;
+use crate::structure::SecondaryStructure;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, Sub};
 
+mod co_optimal;
+mod suboptimal;
+pub use co_optimal::CoOptimal;
+pub use suboptimal::Suboptimal;
+
+// Base pair maximisation, folding through Matrix. The name is the one the
+// folder is registered and reported under, so that several parameter sets
+// can be benchmarked side by side.
 #[derive(Debug, Clone)]
-pub struct Node {
-    default_0: bool,
-    index: [usize; 2],
-    is_match: bool,
-    possible_paths: Vec<[usize; 2]>,
-    value: u8,
-    optimal_k: Option<usize>,
+pub struct Nussinov<S: NussinovScore = u32> {
+    name: String,
+    pub params: NussinovParams<S>,
 }
 
-impl Node {
-    pub fn new() -> Self {
-        Self {
-            default_0: false,
-            index: [0, 0],
-            is_match: false,
-            possible_paths: Vec::new(), 
-            value: 0,
-            optimal_k: None,
-        }
+impl<S: NussinovScore> Default for Nussinov<S> {
+    fn default() -> Self {
+        Nussinov::new("nussinov", NussinovParams::default())
     }
 }
 
-impl Matrix {
-    fn can_pair(a: u8, b: u8) -> bool {
-        match (a, b) {
-                (b'A', b'U') | (b'U', b'A') => true, // A - U
-                (b'G', b'C') | (b'C', b'G') => true, // G - C
-                (b'G', b'U') | (b'U', b'G') => true, // G - U wobble pair
-                _ => false,
-            }
+impl<S: NussinovScore> Nussinov<S> {
+    pub fn new(name: &str, params: NussinovParams<S>) -> Self {
+        Nussinov { name: name.to_string(), params }
     }
 
-    pub fn init_matrix(seq_str: String) -> Self {
+    fn matrix(&self, seq: &RnaSequence) -> Matrix<S> {
+        Matrix::with_params(seq.to_string(), &self.params)
+    }
+}
 
-        let mut matrix: Vec<Vec<Node>> = Vec::new();
-        let mut row: Vec<Node> = Vec::new();
-        
-        let seq_bytes = seq_str.as_bytes();
-        let seq_byte_len = seq_bytes.len();
+impl<S: NussinovScore> Folder for Nussinov<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
 
-        let seq_len = seq_str.len();
+    fn parameters(&self) -> String {
+        self.params.to_string()
+    }
 
-        for i in 0..seq_byte_len { 
-            for j in 0..seq_byte_len {
-                let mut node = Node::new();
-                if j <= i {
-                    node.default_0 = true;
-                } else {
-                    node.index = [i , j];
-                    node.is_match = Self::can_pair(seq_bytes[i], seq_bytes[j]);
-                }
-                row.push(node);
+    fn fold(&self, seq: &RnaSequence) -> Result<SecondaryStructure, Box<dyn std::error::Error>> {
+        Ok(self.matrix(seq).predict_structure())
+    }
+
+    // The prediction of `fold`, then the best scoring other structures, all
+    // from one table.
+    fn candidates(&self, seq: &RnaSequence, k: usize) -> Result<Vec<SecondaryStructure>, Box<dyn std::error::Error>> {
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        let matrix = self.matrix(seq);
+        let prediction = matrix.predict_structure();
+        // every structure is within the optimum of it
+        let optimum = match seq.sizeof() {
+            0 => S::ZERO,
+            len => matrix.score(0, len - 1),
+        };
+        let others: Vec<SecondaryStructure> = matrix
+            .suboptimal(optimum, k)
+            .map(|(structure, _)| structure)
+            .filter(|structure| *structure != prediction)
+            .take(k - 1)
+            .collect();
+        Ok(std::iter::once(prediction).chain(others).collect())
+    }
+}
+
+// Score type of the Nussinov table: integers count pairs exactly, floats
+// allow fractional pair weights.
+pub trait NussinovScore: Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> + fmt::Debug + fmt::Display + Send + Sync + 'static {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_u8(value: u8) -> Self;
+
+    // Whether two scores of the table are the same sum of weights: exact for
+    // integers, up to a relative epsilon for floats, whose sums depend on the
+    // order they were added in.
+    fn approx_eq(self, other: Self) -> bool;
+}
+
+macro_rules! nussinov_score {
+    ($t:ty: $zero:expr, $one:expr, |$a:ident, $b:ident| $approx_eq:expr) => {
+        impl NussinovScore for $t {
+            const ZERO: Self = $zero;
+            const ONE: Self = $one;
+
+            fn from_u8(value: u8) -> Self {
+                value.into()
+            }
+
+            fn approx_eq(self, other: Self) -> bool {
+                let ($a, $b) = (self, other);
+                $approx_eq
             }
-            matrix.push(row.clone());
-            row.clear();
         }
-        return Self { seq_len: seq_len, seq_str: seq_str, matrix: matrix }
+    };
+    (integers $($t:ty),*) => {
+        $(nussinov_score!($t: 0, 1, |a, b| a == b);)*
+    };
+    (floats $($t:ty: $epsilon:expr),*) => {
+        $(nussinov_score!($t: 0.0, 1.0, |a, b| (a - b).abs() <= $epsilon * a.abs().max(b.abs()).max(1.0));)*
+    };
+}
+
+nussinov_score!(integers u16, u32, u64, i32, i64);
+nussinov_score!(floats f32: 1e-4, f64: 1e-9);
+
+// What the Nussinov algorithm maximises: the summed weight of the pairs it
+// allows, with hairpin loops of at least `min_loop` nucleotides. Pairs are
+// unordered, GU allows UG too. Weights must be positive: a pair worth nothing
+// would never be worth forming, so `with_pair` rejects it and `weight` skips
+// one put in `pairs` directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NussinovParams<S: NussinovScore = u32> {
+    pub min_loop: usize,
+    pub pairs: BTreeMap<(u8, u8), S>,
+}
+
+impl<S: NussinovScore> Default for NussinovParams<S> {
+    // AU, GC and GU, one each: plain pair maximisation.
+    fn default() -> Self {
+        NussinovParams { min_loop: 3, pairs: BTreeMap::new() }
+            .with_pair('A', 'U', S::ONE)
+            .with_pair('G', 'C', S::ONE)
+            .with_pair('G', 'U', S::ONE)
+    }
+}
+
+impl<S: NussinovScore> NussinovParams<S> {
+    // Weighted by hydrogen bonds: GC 3, AU 2, GU 1.
+    pub fn weighted() -> Self {
+        NussinovParams::default()
+            .with_pair('G', 'C', S::from_u8(3))
+            .with_pair('A', 'U', S::from_u8(2))
+            .with_pair('G', 'U', S::ONE)
+    }
+
+    pub fn with_min_loop(mut self, min_loop: usize) -> Self {
+        self.min_loop = min_loop;
+        self
+    }
+
+    // Allows a (possibly non-canonical) pair, or changes its weight. Panics
+    // if the weight is not positive, use `without_pair` to disallow a pair.
+    pub fn with_pair(mut self, a: char, b: char, weight: S) -> Self {
+        assert!(weight > S::ZERO, "weight of {}{} must be positive, not {}", a, b, weight);
+        self.pairs.insert(Self::key(a as u8, b as u8), weight);
+        self
+    }
+
+    pub fn without_pair(mut self, a: char, b: char) -> Self {
+        self.pairs.remove(&Self::key(a as u8, b as u8));
+        self
+    }
+
+    fn key(a: u8, b: u8) -> (u8, u8) {
+        let (a, b) = (a.to_ascii_uppercase(), b.to_ascii_uppercase());
+        if a <= b { (a, b) } else { (b, a) }
+    }
+
+    // Weight of a pair of nucleotides, None if they cannot pair.
+    pub fn weight(&self, a: u8, b: u8) -> Option<S> {
+        self.pairs.get(&Self::key(a, b)).copied().filter(|&weight| weight > S::ZERO)
+    }
+}
+
+impl<S: NussinovScore> fmt::Display for NussinovParams<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pairs: Vec<String> = self.pairs.iter().map(|(&(a, b), weight)| format!("{}{}={}", a as char, b as char, weight)).collect();
+        write!(f, "min hairpin loop {}, pairs {}", self.min_loop, pairs.join(" "))
+    }
+}
+
+// Nussinov table. Only the upper triangle is stored, row by row in one flat
+// array, and the traceback recomputes its moves from the scores, so memory
+// grows as n²/2 scores (4 bytes each for the default u32).
+pub struct Matrix<S: NussinovScore = u32> {
+    seq_len: usize,
+    seq: Vec<u8>,
+    params: NussinovParams<S>,
+    scores: Vec<S>,
+}
+
+impl<S: NussinovScore> Matrix<S> {
+    // Position of (i, j), i <= j, in the flat upper triangle.
+    fn index(&self, i: usize, j: usize) -> usize {
+        i * (2 * self.seq_len - i + 1) / 2 + (j - i)
+    }
+
+    // Best score of seq[i..=j], 0 below the diagonal.
+    pub fn score(&self, i: usize, j: usize) -> S {
+        if j < i { S::ZERO } else { self.scores[self.index(i, j)] }
     }
 
-    fn calculate_bifucation(&self, j: usize, k: usize) -> (u8, usize) {
-        let mut bifurcation = 0;
-        let mut optimal_k: usize = 0 as usize;
-        // if k <= j + 1 {
-        //     return (0, j);
-        // }
-        for bifurc in j..k {
-            let bifurc_value = self.matrix[j][bifurc].value + self.matrix[bifurc+1][k].value;
+    pub fn init_matrix(seq_str: String, params: &NussinovParams<S>) -> Self {
+        let seq_len = seq_str.len();
+        let scores = vec![S::ZERO; seq_len * (seq_len + 1) / 2];
+        Self { seq_len, seq: seq_str.into_bytes(), params: params.clone(), scores }
+    }
+
+    // Best split of seq[i..=j] into seq[i..=k] and seq[k+1..=j], first one on ties.
+    fn calculate_bifucation(&self, i: usize, j: usize) -> (S, usize) {
+        let mut bifurcation = S::ZERO;
+        // splitting at the start is the same as leaving it unpaired
+        let mut optimal_k: usize = i;
+        for k in i..j {
+            let bifurc_value = self.score(i, k) + self.score(k + 1, j);
             if bifurc_value > bifurcation {
                 bifurcation = bifurc_value;
-                optimal_k = bifurc;
+                optimal_k = k;
             }
         }
         (bifurcation, optimal_k)
     }
 
+    // Score of seq[i..=j] with i paired to j, if they can pair.
+    fn paired(&self, i: usize, j: usize) -> Option<S> {
+        if j <= i.saturating_add(self.params.min_loop) {
+            return None;
+        }
+        let weight = self.params.weight(self.seq[i], self.seq[j])?;
+        Some(self.score(i + 1, j - 1) + weight)
+    }
+
     pub fn find_values(&mut self) {
         // Fill diagonally: for each subsequence length
-        const MIN_LS: usize = 3;
-        for length in (MIN_LS + 1)..self.seq_len {
+        for length in self.params.min_loop.saturating_add(1)..self.seq_len {
             for i in 0..(self.seq_len - length) {
                 let j = i + length;
-                
-                if j <= i {
-                    continue;
-                }
-                
-                let is_match = self.matrix[i][j].is_match;
-                
-                // Case 1: i unpaired
-                let i_unpaired = if i + 1 <= j { self.matrix[i + 1][j].value } else { 0 };
-                
-                // Case 2: j unpaired
-                let j_unpaired = if j > 0 && i <= j - 1 { self.matrix[i][j - 1].value } else { 0 };
-                
-                // Case 3: i-j paired (if they can pair)
-                let paired = if is_match && i + 1 < j {
-                    self.matrix[i + 1][j - 1].value + 1
-                } else {
-                    0
-                };
-                
-                // Case 4: Bifurcation
-                let (bifurcation, optimal_k) = self.calculate_bifucation(i, j);
-                
-                let max_val = i_unpaired.max(j_unpaired).max(paired).max(bifurcation);
-                
-                let node = &mut self.matrix[i][j];
-                node.value = max_val;
-                
-                // Track which path led to max
-                if paired == max_val && paired > 0 {
-                    node.possible_paths.push([i + 1, j - 1]);
-                }
-                if j_unpaired == max_val {
-                    node.possible_paths.push([i, j - 1]);
-                }
-                if i_unpaired == max_val {
-                    node.possible_paths.push([i + 1, j]);
-                }
-                if bifurcation == max_val {
-                    node.optimal_k = Some(optimal_k);
-                }
+
+                // i or j unpaired
+                let i_unpaired = self.score(i + 1, j);
+                let j_unpaired = self.score(i, j - 1);
+
+                // i-j paired
+                let paired = self.paired(i, j).unwrap_or(S::ZERO);
+
+                // Bifurcation
+                let (bifurcation, _) = self.calculate_bifucation(i, j);
+
+                let index = self.index(i, j);
+                self.scores[index] = [j_unpaired, paired, bifurcation]
+                    .into_iter()
+                    .fold(i_unpaired, |best, value| if value > best { value } else { best });
             }
         }
     }
-//     pub fn find_values(&mut self) {
-//         for i in 1..self.seq_len {
-//             for j in 0..self.seq_len {
-//                 let _counter = 0;
-//                 for k in 1..self.seq_len {
-//                     
-//                     if self.matrix[j][k].default_0 {
-//                         continue;
-//                     }
-//                     
-//                     if k == i + j {
-//                         let is_match = self.matrix[j][k].is_match;
-//                         
-//                         if is_match {
-//                             let k_unpaired  = self.matrix[j][k-1].value;
-//                             let j_unpaired  = self.matrix[j+1][k].value;
-//                             let paired      = self.matrix[j+1][k-1].value;
-//                             let paired_plus = paired + 1;
-//                             
-//                             let (bifurcation, optimal_k) = self.calculate_bifucation(j, k);
-//                             
-//                             let max_val = paired_plus.max(j_unpaired).max(k_unpaired).max(bifurcation);
-//                             
-//                             let node: &mut Node = &mut self.matrix[j][k];
-//                             node.value = max_val;
-// 
-//                             if paired_plus == max_val {
-//                                 node.possible_paths.push([j+1, k-1]);
-//                             }
-//                             if k_unpaired == max_val {
-//                                 node.possible_paths.push([j, k-1]);
-//                             }
-//                             if j_unpaired == max_val {
-//                                 node.possible_paths.push([j+1, k]);
-//                             }
-//                             if bifurcation == max_val {
-//                                 node.optimal_k = Some(optimal_k);
-//                             }
-//                             
-//                         } else {
-//                             let k_unpaired = self.matrix[j][k-1].value;
-//                             let j_unpaired = self.matrix[j+1][k].value;
-// 
-//                             let (bifurcation, optimal_k) = self.calculate_bifucation(j, k);
-//                             
-//                             let max_val = j_unpaired.max(k_unpaired).max(bifurcation);
-// 
-//                             let node: &mut Node = &mut self.matrix[j][k];
-//                             node.value = max_val;
-// 
-//                             if k_unpaired == max_val {
-//                                 node.possible_paths.push([j, k-1]);
-//                             }
-//                             if j_unpaired == max_val {
-//                                 node.possible_paths.push([j+1, k]);
-//                             }
-//                             if bifurcation == max_val {
-//                                 node.optimal_k = Some(optimal_k);
-//                             }
-//                         }
-// 
-//                     } else if k > i + j {
-//                         break;
-//                     }
-//                 }
-// 
-//                 if j == (self.seq_len - 1) - i {
-//                     break;
-//                 }
-//             }
-//         }
-//     }
-
-    pub fn new(seq_str: String) -> Matrix {
-        let mut matrix = Matrix::init_matrix(seq_str);
+
+    pub fn new(seq_str: String) -> Self {
+        Self::with_params(seq_str, &NussinovParams::default())
+    }
+
+    pub fn with_params(seq_str: String, params: &NussinovParams<S>) -> Self {
+        let mut matrix = Matrix::init_matrix(seq_str, params);
         matrix.find_values();
         matrix
     }
 
-    pub fn print_mat(&self) {
-        let mut counter = 0;
-        let bytes = self.seq_str.as_bytes();
-        print!(" ");
-        for c in self.seq_str.chars() {
-            print!(" {}", c);
-        }
-        println!();
-        for i in 0..self.seq_len {
-            print!("{}", bytes[i] as char);
-            for j in 0..self.seq_len {
-                print!(" {}", self.matrix[i][j].value);
-                counter += 1;
-            }
-            println!();
-        }
-        println!("counter: {}", counter);
-        println!("seq_len: {}", self.seq_len);
-        assert_eq!(counter, self.seq_len * self.seq_len);
-    }
-    
+    // Follows one optimal path, taking the first best bifurcation when it is
+    // optimal (which covers leaving i or j unpaired) and pairing i with j
+    // otherwise.
     pub fn traceback(&self) -> Vec<[usize; 2]> {
         let mut base_pairs: Vec<[usize; 2]> = Vec::new();
 
         let mut stack: Vec<[usize; 2]> = Vec::new();
-
-        stack.push([0, self.seq_len - 1]);
+        if self.seq_len > 0 {
+            stack.push([0, self.seq_len - 1]);
+        }
 
         while let Some([i, j]) = stack.pop() {
-            
-            if j < i + 3 {
+            let value = self.score(i, j);
+            if value <= S::ZERO {
                 continue;
             }
 
-            let node = &self.matrix[i][j];
-
-            if let Some(k_opt) = node.optimal_k {
-                stack.push([i, k_opt]);
-                stack.push([k_opt + 1, j]);
-            } else if !node.possible_paths.is_empty() {
-                let next_step = node.possible_paths[0]; 
-                
-                if next_step == [i + 1, j - 1] {
-                    let inner_score = self.matrix[i + 1][j - 1].value;
-                    if node.value == inner_score + 1 {
-                        base_pairs.push([i, j]);
-                    }
-                }
-
-                stack.push(next_step);
+            let (bifurcation, k) = self.calculate_bifucation(i, j);
+            // when rounding leaves neither move at the score, the best one
+            // is taken rather than dropping the interval
+            let pair = match self.paired(i, j) {
+                _ if bifurcation.approx_eq(value) => false,
+                Some(paired) => paired.approx_eq(value) || paired > bifurcation,
+                None => false,
+            };
+            if pair {
+                base_pairs.push([i, j]);
+                stack.push([i + 1, j - 1]);
+            } else {
+                stack.push([i, k]);
+                stack.push([k + 1, j]);
             }
         }
 
         base_pairs
     }
 
+    pub fn predict_structure(&self) -> SecondaryStructure {
+        let pairs: Vec<(usize, usize)> = self.traceback().iter().map(|&[i, j]| (i, j)).collect();
+        SecondaryStructure::from_pairs(self.seq_len, &pairs).expect("traceback pairs are disjoint")
+    }
+
     pub fn predict_fold(&self) -> String {
-        let pairs = self.traceback();
-        let mut fold: Vec<char> = vec!['.'; self.seq_len];
-    
-        for [i, j] in &pairs {
-            fold[*i] = '(';
-            fold[*j] = ')';
-        }
-    
-        let fold_string: String = fold.iter().collect();
+        self.predict_structure()
+            .to_dot_bracket()
+            .expect("a Nussinov structure is nested")
+    }
+}
 
-        fold_string
+// The score table, one row per base.
+impl<S: NussinovScore> fmt::Display for Matrix<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, " ")?;
+        for &c in &self.seq {
+            write!(f, " {}", c as char)?;
+        }
+        writeln!(f)?;
+        for (i, &c) in self.seq.iter().enumerate() {
+            write!(f, "{}", c as char)?;
+            for j in 0..self.seq_len {
+                write!(f, " {}", self.score(i, j))?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "seq_len: {}", self.seq_len)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    // Short random sequences, the same on every run.
+    pub(super) fn sequences(count: usize, max_len: usize) -> Vec<String> {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        (0..count)
+            .map(|n| {
+                (0..1 + n % max_len)
+                    .map(|_| {
+                        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                        b"AUGC"[(state >> 33) as usize % 4] as char
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Every structure of seq[i..=j] the fill recursion allows, as its pairs.
+    pub(super) fn all_structures<S: NussinovScore>(seq: &[u8], params: &NussinovParams<S>, i: usize, j: usize) -> Vec<Vec<(usize, usize)>> {
+        if j < i {
+            return vec![Vec::new()];
+        }
+        let mut structures = all_structures(seq, params, i + 1, j);
+        for k in i.saturating_add(params.min_loop).saturating_add(1)..=j {
+            if params.weight(seq[i], seq[k]).is_none() {
+                continue;
+            }
+            for inside in all_structures(seq, params, i + 1, k - 1) {
+                for outside in all_structures(seq, params, k + 1, j) {
+                    let mut pairs = vec![(i, k)];
+                    pairs.extend(&inside);
+                    pairs.extend(&outside);
+                    structures.push(pairs);
+                }
+            }
+        }
+        structures
+    }
+
+    pub(super) fn score_of<S: NussinovScore>(seq: &[u8], params: &NussinovParams<S>, structure: &SecondaryStructure) -> S {
+        structure.pairs().fold(S::ZERO, |sum, (i, j)| sum + params.weight(seq[i], seq[j]).unwrap())
+    }
+
+    // Dot-brackets of every structure scoring at least `threshold`.
+    pub(super) fn brute_force<S: NussinovScore>(seq: &str, params: &NussinovParams<S>, threshold: S) -> BTreeSet<String> {
+        let bytes = seq.as_bytes();
+        all_structures(bytes, params, 0, seq.len() - 1)
+            .into_iter()
+            .map(|pairs| SecondaryStructure::from_pairs(seq.len(), &pairs).unwrap())
+            .filter(|structure| {
+                let score = score_of(bytes, params, structure);
+                score > threshold || score.approx_eq(threshold)
+            })
+            .map(|structure| structure.to_dot_bracket().unwrap())
+            .collect()
+    }
+
+    pub(super) fn dot_brackets(structures: impl Iterator<Item = SecondaryStructure>) -> Vec<String> {
+        structures.map(|structure| structure.to_dot_bracket().unwrap()).collect()
+    }
+
+    #[test]
+    fn prediction_scores_the_optimum() {
+        let weighted = NussinovParams::<u32>::weighted().with_min_loop(1);
+        let floats = NussinovParams::<f32>::default().with_pair('A', 'U', 0.3).with_pair('G', 'C', 0.7).with_pair('G', 'U', 0.1);
+        for seq in sequences(60, 12) {
+            let matrix = Matrix::with_params(seq.clone(), &weighted);
+            let best = brute_force(&seq, &weighted, matrix.score(0, seq.len() - 1));
+            assert!(best.contains(&matrix.predict_fold()), "{}", seq);
+            let matrix = Matrix::with_params(seq.clone(), &floats);
+            let best = brute_force(&seq, &floats, matrix.score(0, seq.len() - 1));
+            assert!(best.contains(&matrix.predict_fold()), "{}", seq);
+        }
+        assert_eq!(Matrix::<u32>::new(String::new()).predict_fold(), "");
+        assert_eq!(Matrix::<u32>::new("GGGAAACCC".to_string()).predict_fold(), "(((...)))");
+    }
+
+    // When no split of seq[i..=j] scored, the split used to default to 0,
+    // outside the interval, and the traceback went back over the start of
+    // the sequence, giving overlapping pairs.
+    #[test]
+    fn bifurcation_stays_inside_the_interval() {
+        let matrix = Matrix::new("AAAAAGGGAAACCC".to_string());
+        for i in 0..14 {
+            for j in i..14 {
+                let (_, k) = matrix.calculate_bifucation(i, j);
+                assert!((i..=j).contains(&k), "split of {}..={} at {}", i, j, k);
+            }
+        }
+        assert_eq!(matrix.calculate_bifucation(2, 6), (0, 2));
+        assert_eq!(matrix.predict_fold(), ".....(((...)))");
+    }
+
+    #[test]
+    fn weights_must_be_positive() {
+        let mut params = NussinovParams::<i32>::default();
+        params.pairs.insert((b'A', b'U'), 0);
+        assert_eq!(params.weight(b'U', b'A'), None);
+        assert_eq!(params.weight(b'G', b'C'), Some(1));
+        assert_eq!(Matrix::with_params("AAAAUUUU".to_string(), &params).predict_fold(), "........");
+    }
+
+    #[test]
+    #[should_panic(expected = "must be positive")]
+    fn non_positive_weights_are_rejected() {
+        let _ = NussinovParams::<f64>::default().with_pair('G', 'U', -0.5);
+    }
+
+    #[test]
+    fn min_loop_longer_than_the_sequence() {
+        let params = NussinovParams::<u32>::default().with_min_loop(usize::MAX);
+        let matrix = Matrix::with_params("GGGAAAUCC".to_string(), &params);
+        assert_eq!(matrix.predict_fold(), ".........");
+        assert_eq!(matrix.co_optimal().count(), 1);
+        assert_eq!(matrix.suboptimal(0, usize::MAX).count(), 1);
+    }
+}
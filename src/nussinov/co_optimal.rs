@@ -0,0 +1,227 @@
+use super::{Matrix, NussinovScore};
+use crate::structure::SecondaryStructure;
+
+// How seq[i..=j] starts in a structure: i unpaired, or i paired with k.
+// Every structure has exactly one such decomposition, so following these
+// choices gives each structure once (the fill recursion, with both i and j
+// unpaired and any split, would give the same one many times).
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Choice {
+    Unpaired,
+    Pair(usize),
+}
+
+// A structure being traced back: pairs fixed so far, intervals left.
+#[derive(Debug, Clone)]
+pub(super) struct Partial {
+    pub(super) pairs: Vec<(usize, usize)>,
+    pub(super) todo: Vec<(usize, usize)>,
+}
+
+impl Partial {
+    pub(super) fn apply(mut self, i: usize, j: usize, choice: Choice) -> Self {
+        match choice {
+            Choice::Unpaired => self.todo.push((i + 1, j)),
+            Choice::Pair(k) => {
+                self.pairs.push((i, k));
+                self.todo.push((k + 1, j));
+                self.todo.push((i + 1, k - 1));
+            }
+        }
+        self
+    }
+}
+
+// Every maximum score structure of a Matrix, each once, depth first.
+pub struct CoOptimal<'a, S: NussinovScore> {
+    matrix: &'a Matrix<S>,
+    stack: Vec<Partial>,
+}
+
+impl<S: NussinovScore> Iterator for CoOptimal<'_, S> {
+    type Item = SecondaryStructure;
+
+    fn next(&mut self) -> Option<SecondaryStructure> {
+        while let Some(mut partial) = self.stack.pop() {
+            let Some((i, j)) = partial.todo.pop() else {
+                let structure = SecondaryStructure::from_pairs(self.matrix.seq_len, &partial.pairs);
+                return Some(structure.expect("traceback pairs are disjoint"));
+            };
+            // nothing can pair in seq[i..=j], it stays unpaired
+            let choices = self.matrix.optimal_choices(i, j);
+            if choices.is_empty() {
+                self.stack.push(partial);
+                continue;
+            }
+            // pushed in reverse so that the first choice is explored first
+            for &choice in choices.iter().rev() {
+                self.stack.push(partial.clone().apply(i, j, choice));
+            }
+        }
+        None
+    }
+}
+
+// SplitMix64, enough to draw samples without a dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl<S: NussinovScore> Matrix<S> {
+    // Every choice for seq[i..=j] with the best score it leaves possible.
+    // None when nothing can pair.
+    pub(super) fn choices(&self, i: usize, j: usize) -> Vec<(Choice, S)> {
+        if j < i || self.score(i, j) <= S::ZERO {
+            return Vec::new();
+        }
+        let mut choices = vec![(Choice::Unpaired, self.score(i + 1, j))];
+        for k in i.saturating_add(self.params.min_loop).saturating_add(1)..=j {
+            if let Some(paired) = self.paired(i, k) {
+                choices.push((Choice::Pair(k), paired + self.score(k + 1, j)));
+            }
+        }
+        choices
+    }
+
+    // Choices that keep the score of seq[i..=j] at its maximum, compared with
+    // `NussinovScore::approx_eq`. Empty only when nothing can pair: if
+    // rounding leaves no choice at the score, the best one is kept.
+    fn optimal_choices(&self, i: usize, j: usize) -> Vec<Choice> {
+        let value = self.score(i, j);
+        let choices = self.choices(i, j);
+        let optimal: Vec<Choice> = choices
+            .iter()
+            .filter(|&&(_, best)| best.approx_eq(value))
+            .map(|&(choice, _)| choice)
+            .collect();
+        if !optimal.is_empty() {
+            return optimal;
+        }
+        choices
+            .into_iter()
+            .reduce(|best, choice| if choice.1 > best.1 { choice } else { best })
+            .map(|(choice, _)| choice)
+            .into_iter()
+            .collect()
+    }
+
+    // All maximum score structures, the one of `predict_structure` among
+    // them. There can be very many: `.take(n)` for the first n.
+    pub fn co_optimal(&self) -> CoOptimal<'_, S> {
+        let todo = if self.seq_len > 0 { vec![(0, self.seq_len - 1)] } else { Vec::new() };
+        CoOptimal { matrix: self, stack: vec![Partial { pairs: Vec::new(), todo }] }
+    }
+
+    // Number of maximum score structures of each seq[i..=j], in the layout of
+    // `scores`. Counted as f64, they grow exponentially with the length.
+    fn co_optimal_counts(&self) -> Vec<f64> {
+        let mut counts = vec![1.0; self.scores.len()];
+        for length in 1..self.seq_len {
+            for i in 0..(self.seq_len - length) {
+                let j = i + length;
+                let count: f64 = self.optimal_choices(i, j).into_iter().map(|choice| self.choice_count(&counts, i, j, choice)).sum();
+                let index = self.index(i, j);
+                counts[index] = count.max(1.0);
+            }
+        }
+        counts
+    }
+
+    fn choice_count(&self, counts: &[f64], i: usize, j: usize, choice: Choice) -> f64 {
+        let count = |i: usize, j: usize| if j < i { 1.0 } else { counts[self.index(i, j)] };
+        match choice {
+            Choice::Unpaired => count(i + 1, j),
+            Choice::Pair(k) => count(i + 1, k - 1) * count(k + 1, j),
+        }
+    }
+
+    pub fn co_optimal_count(&self) -> f64 {
+        match self.seq_len {
+            0 => 1.0,
+            len => self.co_optimal_counts()[self.index(0, len - 1)],
+        }
+    }
+
+    // `n` maximum score structures drawn uniformly at random (with
+    // replacement), the same ones for the same `seed`.
+    pub fn sample_co_optimal(&self, n: usize, seed: u64) -> Vec<SecondaryStructure> {
+        if self.seq_len == 0 {
+            return vec![SecondaryStructure::unpaired(0); n];
+        }
+        let counts = self.co_optimal_counts();
+        let mut rng = Rng(seed);
+        let mut samples = Vec::with_capacity(n);
+        for _ in 0..n {
+            let mut partial = Partial { pairs: Vec::new(), todo: vec![(0, self.seq_len - 1)] };
+            while let Some((i, j)) = partial.todo.pop() {
+                let choices = self.optimal_choices(i, j);
+                let weights: Vec<f64> = choices.iter().map(|&choice| self.choice_count(&counts, i, j, choice)).collect();
+                let mut target = rng.next_f64() * weights.iter().sum::<f64>();
+                let mut picked = choices.last().copied();
+                for (&choice, weight) in choices.iter().zip(&weights) {
+                    if target < *weight {
+                        picked = Some(choice);
+                        break;
+                    }
+                    target -= weight;
+                }
+                // None when nothing can pair in seq[i..=j]
+                if let Some(choice) = picked {
+                    partial = partial.apply(i, j, choice);
+                }
+            }
+            let structure = SecondaryStructure::from_pairs(self.seq_len, &partial.pairs);
+            samples.push(structure.expect("traceback pairs are disjoint"));
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{brute_force, dot_brackets, score_of, sequences};
+    use super::super::NussinovParams;
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn check_co_optimal<S: NussinovScore>(params: &NussinovParams<S>) {
+        for seq in sequences(60, 12) {
+            let matrix = Matrix::with_params(seq.clone(), params);
+            let optimum = matrix.score(0, seq.len() - 1);
+            let prediction = matrix.predict_structure();
+            assert!(score_of(seq.as_bytes(), params, &prediction).approx_eq(optimum), "{}", seq);
+
+            let expected = brute_force(&seq, params, optimum);
+            let enumerated = dot_brackets(matrix.co_optimal());
+            assert_eq!(enumerated.len(), matrix.co_optimal().count());
+            assert_eq!(enumerated.iter().cloned().collect::<BTreeSet<_>>(), expected, "{}", seq);
+            assert_eq!(enumerated.len(), expected.len(), "{}: enumerated twice", seq);
+            assert_eq!(matrix.co_optimal_count(), expected.len() as f64, "{}", seq);
+            assert!(expected.contains(&prediction.to_dot_bracket().unwrap()));
+            for sample in matrix.sample_co_optimal(5, 1) {
+                assert!(expected.contains(&sample.to_dot_bracket().unwrap()), "{}", seq);
+            }
+        }
+    }
+
+    #[test]
+    fn co_optimal_matches_brute_force() {
+        check_co_optimal(&NussinovParams::<u32>::default());
+        check_co_optimal(&NussinovParams::<u32>::weighted().with_min_loop(1));
+    }
+
+    #[test]
+    fn float_weights_keep_the_optimum() {
+        let params = NussinovParams::<f64>::default().with_pair('A', 'U', 0.1).with_pair('G', 'C', 0.7).with_pair('G', 'U', 0.2);
+        check_co_optimal(&params);
+    }
+}
@@ -0,0 +1,158 @@
+use super::co_optimal::Partial;
+use super::{Matrix, NussinovScore};
+use crate::structure::SecondaryStructure;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+// A partial structure and how far below the optimum its best completion is.
+struct Candidate<S: NussinovScore> {
+    deficit: S,
+    order: usize,
+    partial: Partial,
+}
+
+// Smallest deficit first, then the latest pushed, so that a structure is
+// finished before its siblings are expanded.
+impl<S: NussinovScore> Ord for Candidate<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deficit.partial_cmp(&self.deficit).unwrap_or(Ordering::Equal).then(self.order.cmp(&other.order))
+    }
+}
+
+impl<S: NussinovScore> PartialOrd for Candidate<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: NussinovScore> PartialEq for Candidate<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S: NussinovScore> Eq for Candidate<S> {}
+
+// Structures scoring within `delta` of the optimum (Wuchty et al. 1999), best
+// first, each once, with their score. They are built as they are asked for,
+// and no more than `cap` are given.
+pub struct Suboptimal<'a, S: NussinovScore> {
+    matrix: &'a Matrix<S>,
+    delta: S,
+    cap: usize,
+    optimum: S,
+    heap: BinaryHeap<Candidate<S>>,
+    pushed: usize,
+}
+
+impl<S: NussinovScore> Suboptimal<'_, S> {
+    fn push(&mut self, deficit: S, partial: Partial) {
+        self.pushed += 1;
+        self.heap.push(Candidate { deficit, order: self.pushed, partial });
+    }
+}
+
+impl<S: NussinovScore> Iterator for Suboptimal<'_, S> {
+    type Item = (SecondaryStructure, S);
+
+    fn next(&mut self) -> Option<(SecondaryStructure, S)> {
+        if self.cap == 0 {
+            return None;
+        }
+        while let Some(Candidate { deficit, mut partial, .. }) = self.heap.pop() {
+            // intervals where nothing can pair are left unpaired
+            let next = loop {
+                let Some((i, j)) = partial.todo.pop() else {
+                    break None;
+                };
+                let choices = self.matrix.choices(i, j);
+                if !choices.is_empty() {
+                    break Some((i, j, choices));
+                }
+            };
+            let Some((i, j, choices)) = next else {
+                self.cap -= 1;
+                let structure = SecondaryStructure::from_pairs(self.matrix.seq_len, &partial.pairs);
+                return Some((structure.expect("traceback pairs are disjoint"), self.optimum - deficit));
+            };
+            let value = self.matrix.score(i, j);
+            for (choice, best) in choices {
+                let deficit = deficit + (value - best);
+                if deficit <= self.delta || deficit.approx_eq(self.delta) {
+                    self.push(deficit, partial.clone().apply(i, j, choice));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<S: NussinovScore> Matrix<S> {
+    // Structures at most `delta` below the maximum score, best first: delta 0
+    // gives the co-optimal ones, the maximum score itself every structure.
+    pub fn suboptimal(&self, delta: S, cap: usize) -> Suboptimal<'_, S> {
+        let (optimum, todo) = match self.seq_len {
+            0 => (S::ZERO, Vec::new()),
+            len => (self.score(0, len - 1), vec![(0, len - 1)]),
+        };
+        let mut suboptimal = Suboptimal { matrix: self, delta, cap, optimum, heap: BinaryHeap::new(), pushed: 0 };
+        suboptimal.push(S::ZERO, Partial { pairs: Vec::new(), todo });
+        suboptimal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{brute_force, dot_brackets, score_of, sequences};
+    use super::super::{Nussinov, NussinovParams};
+    use super::*;
+    use crate::folder::Folder;
+    use crate::io::RnaSequence;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn suboptimal_matches_brute_force() {
+        let params = NussinovParams::<u32>::weighted().with_min_loop(1);
+        for seq in sequences(40, 10) {
+            let matrix = Matrix::with_params(seq.clone(), &params);
+            let optimum = matrix.score(0, seq.len() - 1);
+            for delta in [0, 1, 3] {
+                let found: Vec<(SecondaryStructure, u32)> = matrix.suboptimal(delta, usize::MAX).collect();
+                for (structure, score) in &found {
+                    assert_eq!(score_of(seq.as_bytes(), &params, structure), *score, "{}", seq);
+                    assert!(*score + delta >= optimum, "{}: {} is not within {} of {}", seq, score, delta, optimum);
+                }
+                assert!(found.windows(2).all(|w| w[0].1 >= w[1].1), "{}: not best first", seq);
+                let structures = dot_brackets(found.into_iter().map(|(structure, _)| structure));
+                let expected = brute_force(&seq, &params, optimum.saturating_sub(delta));
+                assert_eq!(structures.len(), expected.len(), "{}", seq);
+                assert_eq!(structures.into_iter().collect::<BTreeSet<_>>(), expected, "{}", seq);
+            }
+        }
+    }
+
+    #[test]
+    fn hairpin_and_its_shortenings() {
+        let matrix = Matrix::new("GGGAAACCC".to_string());
+        let found: Vec<(String, u32)> = matrix.suboptimal(1, usize::MAX).map(|(structure, score)| (structure.to_dot_bracket().unwrap(), score)).collect();
+        assert_eq!(found[0], ("(((...)))".to_string(), 3));
+        assert!(found[1..].iter().all(|(_, score)| *score == 2));
+        assert!(found.iter().any(|(fold, _)| fold == "((.....))"));
+        assert_eq!(matrix.suboptimal(1, 2).count(), 2);
+        assert_eq!(matrix.suboptimal(1, 0).count(), 0);
+    }
+
+    #[test]
+    fn candidates_start_with_the_prediction() {
+        let nussinov = Nussinov::<u32>::default();
+        for seq in sequences(30, 40) {
+            let rna_seq = RnaSequence::new("seq", "", &seq);
+            let candidates = nussinov.candidates(&rna_seq, 5).unwrap();
+            assert_eq!(candidates[0], nussinov.fold(&rna_seq).unwrap());
+            assert!(candidates.len() <= 5);
+            let count = candidates.len();
+            assert_eq!(dot_brackets(candidates.into_iter()).into_iter().collect::<BTreeSet<_>>().len(), count, "{}: repeated candidate", seq);
+        }
+        assert!(nussinov.candidates(&RnaSequence::new("seq", "", "GGGAAAUCC"), 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::structure::SecondaryStructure;
+use crate::trna::{Cloverleaf, SprinzlPosition};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// How positions of sequences of different lengths are put side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Axis {
+    #[default]
+    Sprinzl,         // tRNA coordinates from the reference cloverleaf
+    Relative(usize), // position / length, in this many bins
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Coordinate {
+    Bin { index: usize, bins: usize },
+    Sprinzl(SprinzlPosition),
+}
+
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Coordinate::Bin { index, bins } => write!(f, "{}-{}%", index * 100 / bins, (index + 1) * 100 / bins),
+            Coordinate::Sprinzl(position) => write!(f, "{}", position),
+        }
+    }
+}
+
+impl Coordinate {
+    // Label shown under the sparkline: every tenth Sprinzl position, every
+    // fifth of the relative axis.
+    fn tick(&self) -> Option<String> {
+        match self {
+            Coordinate::Bin { index, bins } if index % (bins / 5).max(1) == 0 => Some(format!("{}%", index * 100 / bins)),
+            Coordinate::Sprinzl(position) if position.insertion == 0 && position.number % 10 == 0 => {
+                Some(position.number.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PositionErrors {
+    pub errors: usize,
+    pub total: usize,
+}
+
+impl PositionErrors {
+    pub fn rate(&self) -> f64 {
+        if self.total == 0 { 0.0 } else { self.errors as f64 / self.total as f64 }
+    }
+}
+
+// How often each aligned position is predicted with the wrong pairing state:
+// paired when it should not be, unpaired when it should not be, or paired
+// with another partner.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorProfile {
+    pub positions: BTreeMap<Coordinate, PositionErrors>,
+    pub sequences: usize,
+}
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+impl ErrorProfile {
+    // None when the positions cannot be placed on the axis: a Sprinzl axis
+    // needs a cloverleaf reference.
+    pub fn compare(reference: &SecondaryStructure, predicted: &SecondaryStructure, axis: Axis) -> Option<Self> {
+        let len = reference.len();
+        let coordinates: Vec<Coordinate> = match axis {
+            Axis::Sprinzl => Cloverleaf::find(reference, "")?
+                .sprinzl_numbering(len)
+                .into_iter()
+                .map(Coordinate::Sprinzl)
+                .collect(),
+            Axis::Relative(0) => return None,
+            Axis::Relative(bins) => (0..len).map(|i| Coordinate::Bin { index: i * bins / len, bins }).collect(),
+        };
+
+        let mut profile = ErrorProfile { sequences: 1, ..Default::default() };
+        for (i, coordinate) in coordinates.into_iter().enumerate() {
+            let counts = profile.positions.entry(coordinate).or_default();
+            counts.total += 1;
+            if reference.partner(i) != predicted.partner(i) {
+                counts.errors += 1;
+            }
+        }
+        Some(profile)
+    }
+
+    pub fn merge(&mut self, other: &ErrorProfile) {
+        for (coordinate, counts) in &other.positions {
+            let entry = self.positions.entry(*coordinate).or_default();
+            entry.errors += counts.errors;
+            entry.total += counts.total;
+        }
+        self.sequences += other.sequences;
+    }
+
+    // Positions found in at least half of the sequences, so that rare
+    // insertions do not crowd the sparkline.
+    fn common_positions(&self) -> Vec<(&Coordinate, &PositionErrors)> {
+        self.positions
+            .iter()
+            .filter(|(_, counts)| counts.total * 2 >= self.sequences)
+            .collect()
+    }
+
+    // One bar per common position, from ▁ (no error) to █ (always wrong).
+    pub fn sparkline(&self) -> String {
+        self.common_positions()
+            .into_iter()
+            .map(|(_, counts)| SPARKS[((counts.rate() * SPARKS.len() as f64) as usize).min(SPARKS.len() - 1)])
+            .collect()
+    }
+
+    // Labels lined up under the sparkline.
+    pub fn ruler(&self) -> String {
+        let mut ruler: Vec<char> = Vec::new();
+        for (column, (coordinate, _)) in self.common_positions().into_iter().enumerate() {
+            if ruler.len() > column {
+                continue;
+            }
+            ruler.resize(column, ' ');
+            match coordinate.tick() {
+                Some(tick) => ruler.extend(tick.chars().chain([' '])),
+                None => ruler.push(' '),
+            }
+        }
+        ruler.into_iter().collect::<String>().trim_end().to_string()
+    }
+
+    pub fn print_table(&self) {
+        println!("{:<10}{:>8}{:>8}{:>9}", "Position", "Errors", "Total", "Rate");
+        for (coordinate, counts) in &self.positions {
+            println!("{:<10}{:>8}{:>8}{:>8.1}%", coordinate.to_string(), counts.errors, counts.total, counts.rate() * 100.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trna::tests::{FOLD, SEQ};
+
+    fn structure(fold: &str) -> SecondaryStructure {
+        SecondaryStructure::from_dot_bracket(fold).unwrap()
+    }
+
+    #[test]
+    fn relative_bins() {
+        // the inner pair is lost: positions 1 and 6 are wrong
+        let profile = ErrorProfile::compare(&structure("((....)).."), &structure("(......).."), Axis::Relative(5)).unwrap();
+        let bin = |index| profile.positions[&Coordinate::Bin { index, bins: 5 }];
+        assert_eq!((bin(0), bin(1), bin(3)), (
+            PositionErrors { errors: 1, total: 2 },
+            PositionErrors { errors: 0, total: 2 },
+            PositionErrors { errors: 1, total: 2 },
+        ));
+        assert_eq!(profile.sparkline(), "▅▁▁▅▁");
+        assert_eq!(Coordinate::Bin { index: 1, bins: 5 }.to_string(), "20-40%");
+    }
+
+    #[test]
+    fn sprinzl_positions() {
+        let reference = structure(FOLD);
+        let exact = ErrorProfile::compare(&reference, &reference, Axis::Sprinzl).unwrap();
+        assert_eq!(exact.positions.len(), SEQ.len());
+        assert!(exact.positions.values().all(|counts| counts.errors == 0));
+
+        let unpaired = ErrorProfile::compare(&reference, &SecondaryStructure::unpaired(SEQ.len()), Axis::Sprinzl).unwrap();
+        let at = |number| unpaired.positions[&Coordinate::Sprinzl(SprinzlPosition { number, insertion: 0 })];
+        // acceptor stem and anticodon loop
+        assert_eq!((at(1).errors, at(34).errors), (1, 0));
+
+        let mut merged = exact.clone();
+        merged.merge(&unpaired);
+        assert_eq!((merged.sequences, at(1).total), (2, 1));
+        assert_eq!(merged.positions[&Coordinate::Sprinzl(SprinzlPosition { number: 1, insertion: 0 })].rate(), 0.5);
+        // ticks sit under their position
+        let ten = Coordinate::Sprinzl(SprinzlPosition { number: 10, insertion: 0 });
+        assert_eq!(merged.ruler().find("10"), merged.positions.keys().position(|&coordinate| coordinate == ten));
+    }
+
+    #[test]
+    fn positions_that_cannot_be_placed() {
+        let hairpin = structure("((....))");
+        assert!(ErrorProfile::compare(&hairpin, &hairpin, Axis::Sprinzl).is_none());
+        assert!(ErrorProfile::compare(&hairpin, &hairpin, Axis::Relative(0)).is_none());
+    }
+}
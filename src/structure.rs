@@ -0,0 +1,286 @@
+use std::str::FromStr;
+
+// Bracket types in the order they are used for nesting levels: the first pair
+// holds the nested structure, the others the pseudoknotted pairs.
+pub const BRACKETS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
+
+fn is_unpaired(c: char) -> bool {
+    matches!(c, '.' | '-' | ',' | ':' | '_' | '~')
+}
+
+// Index of the bracket type and whether the symbol opens a pair. Letters are
+// used after the four bracket types: A..Z open and a..z close.
+fn bracket_type(c: char) -> Option<(usize, bool)> {
+    if let Some(k) = BRACKETS.iter().position(|&(open, _)| open == c) {
+        return Some((k, true));
+    }
+    if let Some(k) = BRACKETS.iter().position(|&(_, close)| close == c) {
+        return Some((k, false));
+    }
+    if c.is_ascii_uppercase() {
+        return Some((BRACKETS.len() + (c as u8 - b'A') as usize, true));
+    }
+    if c.is_ascii_lowercase() {
+        return Some((BRACKETS.len() + (c as u8 - b'a') as usize, false));
+    }
+    None
+}
+
+fn bracket_symbols(kind: usize) -> Option<(char, char)> {
+    if kind < BRACKETS.len() {
+        return Some(BRACKETS[kind]);
+    }
+    let letter = kind - BRACKETS.len();
+    if letter < 26 {
+        return Some(((b'A' + letter as u8) as char, (b'a' + letter as u8) as char));
+    }
+    None
+}
+
+fn crosses(a: (usize, usize), b: (usize, usize)) -> bool {
+    (a.0 < b.0 && b.0 < a.1 && a.1 < b.1) || (b.0 < a.0 && a.0 < b.1 && b.1 < a.1)
+}
+
+// A secondary structure as a pair table: for each position the index of its
+// partner, or None if unpaired. Positions are 0-based, pseudoknots allowed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SecondaryStructure {
+    table: Vec<Option<usize>>,
+}
+
+impl SecondaryStructure {
+    pub fn unpaired(len: usize) -> Self {
+        SecondaryStructure { table: vec![None; len] }
+    }
+
+    pub fn from_dot_bracket(fold: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let symbols: Vec<char> = fold.chars().collect();
+        let mut table = vec![None; symbols.len()];
+        let mut stacks: Vec<Vec<usize>> = Vec::new();
+
+        for (i, &c) in symbols.iter().enumerate() {
+            if is_unpaired(c) {
+                continue;
+            }
+            let (kind, opens) = bracket_type(c)
+                .ok_or_else(|| format!("Invalid structure symbol '{}' at position {}", c, i + 1))?;
+            if stacks.len() <= kind {
+                stacks.resize(kind + 1, Vec::new());
+            }
+            if opens {
+                stacks[kind].push(i);
+            } else {
+                let j = stacks[kind]
+                    .pop()
+                    .ok_or_else(|| format!("Unmatched '{}' at position {}", c, i + 1))?;
+                table[i] = Some(j);
+                table[j] = Some(i);
+            }
+        }
+
+        if let Some(&i) = stacks.iter().flatten().min() {
+            return Err(format!("Unmatched '{}' at position {}", symbols[i], i + 1).into());
+        }
+
+        Ok(SecondaryStructure { table })
+    }
+
+    // 0-based pairs, in any order.
+    pub fn from_pairs(len: usize, pairs: &[(usize, usize)]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut table = vec![None; len];
+        for &(i, j) in pairs {
+            if i >= len || j >= len || i == j {
+                return Err(format!("Invalid pair {}-{} for length {}", i + 1, j + 1, len).into());
+            }
+            if table[i].is_some_and(|k| k != j) || table[j].is_some_and(|k| k != i) {
+                return Err(format!("Position of pair {}-{} is already paired", i + 1, j + 1).into());
+            }
+            table[i] = Some(j);
+            table[j] = Some(i);
+        }
+        Ok(SecondaryStructure { table })
+    }
+
+    // Partner column of CT and BPSEQ files: 1-based, 0 for unpaired.
+    pub fn from_partners(partners: &[usize]) -> Result<Self, Box<dyn std::error::Error>> {
+        let table: Vec<Option<usize>> = partners.iter().map(|&p| p.checked_sub(1)).collect();
+        Self::from_pair_table(table)
+    }
+
+    pub fn from_pair_table(table: Vec<Option<usize>>) -> Result<Self, Box<dyn std::error::Error>> {
+        for (i, &partner) in table.iter().enumerate() {
+            let Some(j) = partner else { continue };
+            if table.get(j) != Some(&Some(i)) || i == j {
+                return Err(format!("Inconsistent pair {}-{}", i + 1, j + 1).into());
+            }
+        }
+        Ok(SecondaryStructure { table })
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    pub fn partner(&self, i: usize) -> Option<usize> {
+        self.table.get(i).copied().flatten()
+    }
+
+    pub fn pair_table(&self) -> &[Option<usize>] {
+        &self.table
+    }
+
+    // Pairs (i, j) with i < j, from 5' to 3'.
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.table
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &partner)| partner.filter(|&j| j > i).map(|j| (i, j)))
+    }
+
+    pub fn pair_count(&self) -> usize {
+        self.pairs().count()
+    }
+
+    pub fn is_nested(&self) -> bool {
+        let mut open: Vec<usize> = Vec::new();
+        for (i, &partner) in self.table.iter().enumerate() {
+            match partner {
+                Some(j) if j > i => open.push(j),
+                Some(_) if open.pop() != Some(i) => return false,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    // Pairs are taken from 5' to 3' and get the first level they do not
+    // cross; level 0 is a nested structure.
+    fn levels(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut levels: Vec<Vec<(usize, usize)>> = Vec::new();
+        for (i, j) in self.pairs() {
+            match levels.iter_mut().find(|level| level.iter().all(|&pair| !crosses(pair, (i, j)))) {
+                Some(level) => level.push((i, j)),
+                None => levels.push(vec![(i, j)]),
+            }
+        }
+        levels
+    }
+
+    // Whether some pairs cross, whatever brackets they were written with.
+    pub fn is_pseudoknotted(&self) -> bool {
+        self.levels().len() > 1
+    }
+
+    // The structure without its pseudoknotted pairs: those rendered with
+    // other brackets than `()` by to_dot_bracket.
+    pub fn nested(&self) -> Self {
+        let mut nested = Self::unpaired(self.table.len());
+        for (i, j) in self.levels().into_iter().next().unwrap_or_default() {
+            nested.table[i] = Some(j);
+            nested.table[j] = Some(i);
+        }
+        nested
+    }
+
+    // A nested structure only uses `()`.
+    pub fn to_dot_bracket(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut fold = vec!['.'; self.table.len()];
+        for (kind, level) in self.levels().into_iter().enumerate() {
+            for (i, j) in level {
+                let (open, close) = bracket_symbols(kind)
+                    .ok_or_else(|| format!("Too many crossing pairs to render pair {}-{}", i + 1, j + 1))?;
+                fold[i] = open;
+                fold[j] = close;
+            }
+        }
+        Ok(fold.into_iter().collect())
+    }
+}
+
+impl FromStr for SecondaryStructure {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(fold: &str) -> Result<Self, Self::Err> {
+        Self::from_dot_bracket(fold)
+    }
+}
+
+// Heuristic used to tell structure lines from sequence lines: only structure
+// symbols, with dots and brackets outnumbering the letters (letter pairs are
+// valid pseudoknot brackets, but a sequence is almost only letters).
+pub fn looks_like_dot_bracket(line: &str) -> bool {
+    let mut letters = 0;
+    let mut symbols = 0;
+    for c in line.chars() {
+        if c.is_ascii_alphabetic() {
+            letters += 1;
+        } else if is_unpaired(c) || bracket_type(c).is_some() {
+            symbols += 1;
+        } else {
+            return false;
+        }
+    }
+    symbols > letters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_agree() {
+        let structure: SecondaryStructure = "((..[[..))..]]".parse().unwrap();
+        assert_eq!(structure.pairs().collect::<Vec<_>>(), [(0, 9), (1, 8), (4, 13), (5, 12)]);
+        assert_eq!((structure.partner(4), structure.partner(2), structure.partner(99)), (Some(13), None, None));
+        assert_eq!(SecondaryStructure::from_pairs(14, &[(5, 12), (13, 4), (0, 9), (1, 8)]).unwrap(), structure);
+        let partners: Vec<usize> = structure.pair_table().iter().map(|p| p.map_or(0, |j| j + 1)).collect();
+        assert_eq!(SecondaryStructure::from_partners(&partners).unwrap(), structure);
+        assert_eq!(SecondaryStructure::from_dot_bracket("").unwrap(), SecondaryStructure::unpaired(0));
+    }
+
+    #[test]
+    fn crossing_pairs_decide_the_brackets() {
+        // letters and other brackets are read whatever they are, then written
+        // back by nesting level
+        let structure = SecondaryStructure::from_dot_bracket("<<..AA..>>..aa").unwrap();
+        assert!(structure.is_pseudoknotted() && !structure.is_nested());
+        assert_eq!(structure.to_dot_bracket().unwrap(), "((..[[..))..]]");
+        assert_eq!(structure.nested().to_dot_bracket().unwrap(), "((......))....");
+
+        let three_levels = SecondaryStructure::from_dot_bracket("(.[.{.).].}").unwrap();
+        assert_eq!(three_levels.to_dot_bracket().unwrap(), "(.[.{.).].}");
+        let nested = SecondaryStructure::from_dot_bracket("[.{..}.]").unwrap();
+        assert!(nested.is_nested() && !nested.is_pseudoknotted());
+        assert_eq!(nested.to_dot_bracket().unwrap(), "(.(..).)");
+    }
+
+    #[test]
+    fn invalid_structures_are_errors() {
+        let error = |fold: &str| SecondaryStructure::from_dot_bracket(fold).unwrap_err().to_string();
+        assert_eq!(error("((..)"), "Unmatched '(' at position 1");
+        assert_eq!(error("(..))"), "Unmatched ')' at position 5");
+        assert_eq!(error("(..)]"), "Unmatched ']' at position 5");
+        assert_eq!(error("(.*.)"), "Invalid structure symbol '*' at position 3");
+        assert!(SecondaryStructure::from_pairs(4, &[(0, 3), (1, 3)]).is_err());
+        assert!(SecondaryStructure::from_pairs(4, &[(0, 4)]).is_err());
+        assert!(SecondaryStructure::from_partners(&[4, 0, 0, 2]).is_err());
+
+        // 31 mutually crossing pairs need more bracket types than there are
+        let crossing: Vec<(usize, usize)> = (0..31).map(|k| (k, k + 31)).collect();
+        let structure = SecondaryStructure::from_pairs(62, &crossing).unwrap();
+        assert!(structure.to_dot_bracket().is_err());
+    }
+
+    #[test]
+    fn structure_lines_are_told_from_sequences() {
+        assert!(looks_like_dot_bracket("((..[[..))..]]"));
+        assert!(looks_like_dot_bracket("((.A)a)"));
+        assert!(!looks_like_dot_bracket("GGAAACC"));
+        assert!(!looks_like_dot_bracket("GG-AAA"));
+        assert!(!looks_like_dot_bracket("((..*))"));
+    }
+}
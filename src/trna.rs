@@ -0,0 +1,383 @@
+use crate::elements::{ElementKind, Recovery, decompose};
+use crate::structure::SecondaryStructure;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Arm {
+    Acceptor,
+    D,
+    Anticodon,
+    Variable,
+    T,
+}
+
+impl fmt::Display for Arm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Arm::Acceptor => "acceptor stem",
+            Arm::D => "D arm",
+            Arm::Anticodon => "anticodon arm",
+            Arm::Variable => "variable arm",
+            Arm::T => "T arm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// One arm: the positions it spans (0-based, inclusive) and its pairs. The
+// acceptor stem spans the whole tRNA but only holds the pairs outside the
+// other arms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArmRegion {
+    pub arm: Arm,
+    pub start: usize,
+    pub end: usize,
+    pub pairs: Vec<(usize, usize)>,
+}
+
+impl ArmRegion {
+    // Share of the arm's pairs found in `predicted`.
+    fn recovered(&self, predicted: &SecondaryStructure, helix_threshold: f64) -> bool {
+        let found = self.pairs.iter().filter(|&&(i, j)| predicted.partner(i) == Some(j)).count();
+        found as f64 >= helix_threshold * self.pairs.len() as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cloverleaf {
+    pub acceptor: ArmRegion,
+    pub d_arm: ArmRegion,
+    pub anticodon_arm: ArmRegion,
+    pub variable_arm: Option<ArmRegion>, // paired in class II tRNAs only
+    pub t_arm: ArmRegion,
+    pub anticodon_position: Option<usize>, // first base of the anticodon
+    pub anticodon: Option<String>,
+}
+
+// Arms recovered by one prediction of a cloverleaf reference.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CloverleafRecovery {
+    pub arms: BTreeMap<Arm, Recovery>,
+    pub cloverleaf: Recovery,
+}
+
+impl CloverleafRecovery {
+    pub fn merge(&mut self, other: &CloverleafRecovery) {
+        for (arm, counts) in &other.arms {
+            let entry = self.arms.entry(*arm).or_default();
+            entry.found += counts.found;
+            entry.total += counts.total;
+        }
+        self.cloverleaf.found += other.cloverleaf.found;
+        self.cloverleaf.total += other.cloverleaf.total;
+    }
+
+    pub fn get(&self, arm: Arm) -> Recovery {
+        self.arms.get(&arm).copied().unwrap_or_default()
+    }
+}
+
+// Unpaired nucleotides a side of the acceptor stem can have between two pairs.
+const MAX_ACCEPTOR_LOOP: usize = 3;
+
+impl Cloverleaf {
+    // A cloverleaf is a single helix from the ends (the acceptor stem) closing
+    // a 3 or 4-way multiloop and no other multiloop: D, anticodon, optional
+    // variable and T arms from 5' to 3'. The acceptor stem may hold bulges and
+    // mismatches of up to 3 nt a side, as some mitochondrial tRNAs do, but a
+    // larger loop before the multiloop makes its closing helix another one.
+    // Pseudoknotted pairs are ignored.
+    // The anticodon is the middle of a 7 nt anticodon loop (positions 34-36).
+    pub fn find(structure: &SecondaryStructure, seq: &str) -> Option<Self> {
+        let nested = structure.nested();
+        let elements = decompose(&nested);
+
+        let exterior = elements.iter().find(|element| element.kind == ElementKind::ExteriorLoop)?;
+        let &[(acceptor_start, acceptor_end)] = exterior.pairs.as_slice() else {
+            return None;
+        };
+        let mut multiloops = elements.iter().filter(|element| element.kind == ElementKind::Multiloop);
+        let (Some(multiloop), None) = (multiloops.next(), multiloops.next()) else {
+            return None;
+        };
+        let (mut i, mut j) = (acceptor_start, acceptor_end);
+        while (i, j) != multiloop.pairs[0] {
+            let k = (i + 1..j).find(|&k| nested.partner(k).is_some())?;
+            let l = nested.partner(k).filter(|&l| k < l && l < j)?;
+            if k - i - 1 > MAX_ACCEPTOR_LOOP || j - l - 1 > MAX_ACCEPTOR_LOOP {
+                return None;
+            }
+            (i, j) = (k, l);
+        }
+
+        let region = |arm: Arm, (start, end): (usize, usize)| ArmRegion {
+            arm,
+            start,
+            end,
+            pairs: nested.pairs().filter(|&(i, j)| start <= i && j <= end).collect(),
+        };
+        let (d_arm, anticodon_arm, variable_arm, t_arm) = match multiloop.pairs[1..] {
+            [d, anticodon, t] => (region(Arm::D, d), region(Arm::Anticodon, anticodon), None, region(Arm::T, t)),
+            [d, anticodon, variable, t] => (
+                region(Arm::D, d),
+                region(Arm::Anticodon, anticodon),
+                Some(region(Arm::Variable, variable)),
+                region(Arm::T, t),
+            ),
+            _ => return None,
+        };
+
+        let inner_arms = [Some(&d_arm), Some(&anticodon_arm), variable_arm.as_ref(), Some(&t_arm)];
+        let acceptor = ArmRegion {
+            arm: Arm::Acceptor,
+            start: acceptor_start,
+            end: acceptor_end,
+            pairs: nested
+                .pairs()
+                .filter(|&(i, _)| inner_arms.iter().flatten().all(|arm| i < arm.start || arm.end < i))
+                .collect(),
+        };
+
+        let anticodon_loop = elements.iter().find(|element| {
+            element.kind == ElementKind::Hairpin && anticodon_arm.start <= element.pairs[0].0 && element.pairs[0].1 <= anticodon_arm.end
+        });
+        let anticodon_position = match anticodon_loop.map(|element| element.segments.as_slice()) {
+            Some(&[(start, end)]) if end - start + 1 == 7 => Some(start + 2),
+            _ => None,
+        };
+        let anticodon = anticodon_position.and_then(|k| seq.get(k..k + 3)).map(String::from);
+
+        Some(Cloverleaf { acceptor, d_arm, anticodon_arm, variable_arm, t_arm, anticodon_position, anticodon })
+    }
+
+    pub fn arms(&self) -> Vec<&ArmRegion> {
+        let arms = [Some(&self.acceptor), Some(&self.d_arm), Some(&self.anticodon_arm), self.variable_arm.as_ref(), Some(&self.t_arm)];
+        arms.into_iter().flatten().collect()
+    }
+
+    // An arm is right when at least `helix_threshold` of its pairs are
+    // predicted; the cloverleaf is recovered when the prediction is a
+    // cloverleaf and every arm is right.
+    pub fn compare(&self, predicted: &SecondaryStructure, helix_threshold: f64) -> CloverleafRecovery {
+        let mut recovery = CloverleafRecovery::default();
+        let mut all_arms = true;
+        for arm in self.arms() {
+            let found = arm.recovered(predicted, helix_threshold);
+            all_arms &= found;
+            let counts = recovery.arms.entry(arm.arm).or_default();
+            counts.total += 1;
+            counts.found += found as usize;
+        }
+        let is_cloverleaf = Self::find(predicted, "").is_some();
+        recovery.cloverleaf = Recovery { found: (all_arms && is_cloverleaf) as usize, total: 1 };
+        recovery
+    }
+}
+
+// Standard tRNA coordinate (Sprinzl et al. 1998). Insertions after a
+// position are lettered: 17a, 17b, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SprinzlPosition {
+    pub number: i32,
+    pub insertion: u16,
+}
+
+impl fmt::Display for SprinzlPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.insertion {
+            0 => write!(f, "{}", self.number),
+            1..=26 => write!(f, "{}{}", self.number, (b'a' + (self.insertion - 1) as u8) as char),
+            k => write!(f, "{}+{}", self.number, k),
+        }
+    }
+}
+
+// Numbers `len` positions against the canonical `first..=last`: from the left
+// up to `split`, from the right after it. Extra positions become insertions
+// after `split`, missing ones are taken out around it.
+fn number_segment(len: usize, first: i32, last: i32, split: i32) -> Vec<SprinzlPosition> {
+    let at = |number| SprinzlPosition { number, insertion: 0 };
+    let left: Vec<i32> = (first..=split.min(last)).collect();
+    let right: Vec<i32> = ((split + 1).max(first)..=last).collect();
+    let canonical = left.len() + right.len();
+
+    if len >= canonical {
+        let anchor = if left.is_empty() { first - 1 } else { split };
+        let insertions = (1..=len - canonical).map(|k| SprinzlPosition { number: anchor, insertion: k as u16 });
+        return left.into_iter().map(at).chain(insertions).chain(right.into_iter().map(at)).collect();
+    }
+    let missing = canonical - len;
+    let drop_right = (missing / 2).max(missing.saturating_sub(left.len())).min(right.len());
+    let drop_left = missing - drop_right;
+    left[..left.len() - drop_left]
+        .iter()
+        .chain(&right[drop_right..])
+        .map(|&number| at(number))
+        .collect()
+}
+
+// Pairs stacked on the outer pair of an arm.
+fn stacked(arm: &ArmRegion) -> usize {
+    arm.pairs
+        .iter()
+        .enumerate()
+        .take_while(|&(k, &(i, j))| i == arm.start + k && j + k == arm.end)
+        .count()
+}
+
+// An arm numbered `first..=last` with a `stem` pair long stem; its loop
+// takes insertions after `split`. A shorter stem keeps the outer numbers, a
+// longer one gets insertions on the outer side (26a, 43a for the anticodon).
+fn number_arm(arm: &ArmRegion, first: i32, last: i32, stem: i32, split: i32) -> Vec<SprinzlPosition> {
+    let n = stacked(arm);
+    let paired = (n as i32).min(stem);
+    let (split_5p, split_3p) = if n as i32 > stem { (first - 1, last) } else { (first + stem - 1, last - stem) };
+    let mut numbers = number_segment(n, first, first + stem - 1, split_5p);
+    numbers.extend(number_segment(arm.end + 1 - arm.start - 2 * n, first + paired, last - paired, split));
+    numbers.extend(number_segment(n, last - stem + 1, last, split_3p));
+    numbers
+}
+
+// The anticodon arm, numbered from the ends of its stem around the loop so
+// that the anticodon stays 34-36 when the stem is short or bulged: the loop
+// is 32-38 with insertions after 36, the stem sides 27-31 and 39-43, short
+// on their outer end or with insertions on it. Arms that branch are numbered
+// as the other arms.
+fn number_anticodon_arm(arm: &ArmRegion) -> Vec<SprinzlPosition> {
+    let is_helix = arm.pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[1].1 < w[0].1);
+    let Some(&(p, q)) = arm.pairs.last().filter(|_| is_helix) else {
+        return number_arm(arm, 27, 43, 5, 38);
+    };
+    let mut numbers = number_segment(p + 1 - arm.start, 27, 31, 26);
+    numbers.extend(number_segment(q - p - 1, 32, 38, 36));
+    numbers.extend(number_segment(arm.end + 1 - q, 39, 43, 43));
+    numbers
+}
+
+impl Cloverleaf {
+    // Sprinzl coordinate of each of the `len` positions of the sequence the
+    // cloverleaf was found in. Each region is numbered against its standard
+    // span: acceptor 1-7/66-72, D arm 10-25, 26, anticodon arm 27-43,
+    // variable region 44-48, T arm 49-65 and 73-76. Extra nucleotides of long
+    // variable arms are numbered as insertions after 47 rather than e1, e2...
+    pub fn sprinzl_numbering(&self, len: usize) -> Vec<SprinzlPosition> {
+        let acceptor = stacked(&self.acceptor);
+        let variable_start = self.anticodon_arm.end + 1;
+        let acceptor_3p = self.acceptor.end + 1 - acceptor;
+        let last = 72 - acceptor.min(7) as i32;
+
+        let mut numbers = number_segment(self.acceptor.start, 1 - self.acceptor.start as i32, 0, 0);
+        numbers.extend(number_segment(acceptor, 1, 7, 7));
+        numbers.extend(number_segment(self.d_arm.start - self.acceptor.start - acceptor, acceptor.min(7) as i32 + 1, 9, 9));
+        numbers.extend(number_arm(&self.d_arm, 10, 25, 4, 17));
+        numbers.extend(number_segment(self.anticodon_arm.start - self.d_arm.end - 1, 26, 26, 26));
+        numbers.extend(number_anticodon_arm(&self.anticodon_arm));
+        numbers.extend(number_segment(self.t_arm.start - variable_start, 44, 48, 47));
+        numbers.extend(number_arm(&self.t_arm, 49, 65, 5, 57));
+        numbers.extend(number_segment(acceptor_3p - self.t_arm.end - 1, 66, last, last));
+        numbers.extend(number_segment(acceptor, 66, 72, 65));
+        numbers.extend(number_segment(len.saturating_sub(self.acceptor.end + 1), 73, 76, 76));
+
+        // two neighbouring segments may both insert after the same position
+        for k in 1..numbers.len() {
+            let previous = numbers[k - 1];
+            if numbers[k].number == previous.number && numbers[k].insertion <= previous.insertion {
+                numbers[k].insertion = previous.insertion + 1;
+            }
+        }
+        numbers
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // tdbR00000365, a class I tRNA without its 3' CCA
+    pub(crate) const SEQ: &str = "AAAUAUGAAGCGAUUUAUUGCAAUUAGUUUCGACCUAAUCUUAGGUGAAAUUCACCCAUAUUUUCCA";
+    pub(crate) const FOLD: &str = "(((((((..((((....)))).(((((.......)))))....((((.....)))))))))))....";
+
+    fn structure(fold: &str) -> SecondaryStructure {
+        SecondaryStructure::from_dot_bracket(fold).unwrap()
+    }
+
+    #[test]
+    fn finds_the_arms() {
+        let cloverleaf = Cloverleaf::find(&structure(FOLD), SEQ).unwrap();
+        assert_eq!((cloverleaf.acceptor.start, cloverleaf.acceptor.end, cloverleaf.acceptor.pairs.len()), (0, 62, 7));
+        assert_eq!((cloverleaf.d_arm.start, cloverleaf.d_arm.end, cloverleaf.d_arm.pairs.len()), (9, 20, 4));
+        assert_eq!((cloverleaf.anticodon_arm.start, cloverleaf.anticodon_arm.end), (22, 38));
+        assert_eq!((cloverleaf.t_arm.start, cloverleaf.t_arm.end), (43, 55));
+        assert!(cloverleaf.variable_arm.is_none());
+        assert_eq!(cloverleaf.anticodon_position, Some(29));
+        assert_eq!(cloverleaf.anticodon.as_deref(), Some("UCG"));
+    }
+
+    #[test]
+    fn other_structures_are_not_cloverleaves() {
+        // one hairpin, two arms only, no acceptor stem around the arms, and
+        // arms closed by a helix of their own inside the acceptor stem
+        let inner_helix = format!("((((......(((({}))))......))))", &FOLD[7..56]);
+        for fold in ["((((....))))", "((((((....))((....))))))", "..((((....))))((((....))))((((....)))).."] {
+            assert!(Cloverleaf::find(&structure(fold), "").is_none(), "{}", fold);
+        }
+        assert!(Cloverleaf::find(&structure(&inner_helix), "").is_none());
+        // a 3 nt bulge still leaves the multiloop to the acceptor stem
+        let bulged = format!("(((((...(({}))))))){}", &FOLD[7..56], &FOLD[63..]);
+        assert!(Cloverleaf::find(&structure(&bulged), "").is_some());
+    }
+
+    #[test]
+    fn recovered_arms() {
+        let cloverleaf = Cloverleaf::find(&structure(FOLD), SEQ).unwrap();
+        let exact = cloverleaf.compare(&structure(FOLD), 0.7);
+        assert_eq!((exact.cloverleaf.found, exact.get(Arm::T).found), (1, 1));
+
+        // T arm lost: every other arm is right, the cloverleaf is not
+        let mut no_t_arm = FOLD.to_string();
+        no_t_arm.replace_range(43..56, &".".repeat(13));
+        let recovery = cloverleaf.compare(&structure(&no_t_arm), 0.7);
+        assert_eq!((recovery.get(Arm::T).found, recovery.get(Arm::D).found, recovery.get(Arm::Anticodon).found), (0, 1, 1));
+        assert_eq!(recovery.cloverleaf, Recovery { found: 0, total: 1 });
+    }
+
+    fn sprinzl(fold: &str) -> Vec<String> {
+        let cloverleaf = Cloverleaf::find(&structure(fold), SEQ).unwrap();
+        let numbers = cloverleaf.sprinzl_numbering(SEQ.len());
+        assert!(numbers.windows(2).all(|w| w[0] < w[1]), "{}", fold);
+        numbers.iter().map(|number| number.to_string()).collect()
+    }
+
+    #[test]
+    fn sprinzl_numbering() {
+        let numbers = sprinzl(FOLD);
+        assert_eq!(numbers.len(), SEQ.len());
+        assert_eq!((numbers[0].as_str(), numbers[6].as_str(), numbers[21].as_str()), ("1", "7", "26"));
+        assert_eq!(numbers[29..32], ["34", "35", "36"]);
+        assert_eq!(numbers[62..], ["72", "73", "74", "75", "76"]);
+
+        // a 4 pair anticodon stem keeps the anticodon at 34-36
+        let mut short_stem = FOLD.to_string();
+        short_stem.replace_range(22..23, ".");
+        short_stem.replace_range(38..39, ".");
+        let numbers = sprinzl(&short_stem);
+        assert_eq!(numbers[23..27], ["28", "29", "30", "31"]);
+        assert_eq!(numbers[29..32], ["34", "35", "36"]);
+    }
+
+    #[test]
+    fn insertions_are_lettered() {
+        assert_eq!(SprinzlPosition { number: 17, insertion: 0 }.to_string(), "17");
+        assert_eq!(SprinzlPosition { number: 17, insertion: 2 }.to_string(), "17b");
+        assert_eq!(SprinzlPosition { number: 47, insertion: 30 }.to_string(), "47+30");
+
+        // a longer anticodon loop takes its insertions after 36
+        let seq = format!("{}AA{}", &SEQ[..32], &SEQ[32..]);
+        let fold = format!("{}..{}", &FOLD[..32], &FOLD[32..]);
+        let cloverleaf = Cloverleaf::find(&structure(&fold), &seq).unwrap();
+        assert_eq!(cloverleaf.anticodon_position, None);
+        let numbers: Vec<String> = cloverleaf.sprinzl_numbering(seq.len()).iter().map(|number| number.to_string()).collect();
+        assert_eq!(numbers[27..36], ["32", "33", "34", "35", "36", "36a", "36b", "37", "38"]);
+    }
+}
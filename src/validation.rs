@@ -0,0 +1,182 @@
+use crate::io::RnaSequence;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    MissingStructure,
+    Unbalanced(String),
+    LengthMismatch { sequence: usize, structure: usize },
+    SharpTurn { i: usize, j: usize },                 // 1-based positions
+    NonCanonical { i: usize, j: usize, pair: String }, // 1-based positions
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::MissingStructure => write!(f, "no experimental structure"),
+            Issue::Unbalanced(msg) => write!(f, "unbalanced structure: {}", msg),
+            Issue::LengthMismatch { sequence, structure } => {
+                write!(f, "sequence has {} nt, structure has {}", sequence, structure)
+            }
+            Issue::SharpTurn { i, j } => write!(f, "sharp turn {}-{}", i, j),
+            Issue::NonCanonical { i, j, pair } => write!(f, "non-canonical pair {} at {}-{}", pair, i, j),
+        }
+    }
+}
+
+// What to do with records that have at least one issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    Keep,
+    Exclude,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordIssues {
+    pub id: String,
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub total: usize,
+    pub excluded: usize,
+    pub records: Vec<RecordIssues>,
+}
+
+fn is_canonical(a: u8, b: u8) -> bool {
+    // A=0, U=1, G=2, C=3
+    matches!((a, b), (0, 1) | (1, 0) | (2, 3) | (3, 2) | (2, 1) | (1, 2))
+}
+
+// Problems of one record's experimental structure. Pairs with an unknown base
+// are not reported as non-canonical.
+pub fn check(seq: &RnaSequence, min_hairpin: usize) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if let Some(invalid) = &seq.invalid_fold {
+        let structure_len = invalid.fold.chars().count();
+        if structure_len != seq.sizeof() {
+            issues.push(Issue::LengthMismatch { sequence: seq.sizeof(), structure: structure_len });
+        }
+        issues.push(Issue::Unbalanced(invalid.error.clone()));
+        return issues;
+    }
+    let Some(exp_structure) = &seq.structure else {
+        issues.push(Issue::MissingStructure);
+        return issues;
+    };
+
+    if exp_structure.len() != seq.sizeof() {
+        issues.push(Issue::LengthMismatch { sequence: seq.sizeof(), structure: exp_structure.len() });
+    }
+
+    let seq_str = seq.to_string();
+    for (i, j) in exp_structure.pairs() {
+        if j - i - 1 < min_hairpin {
+            issues.push(Issue::SharpTurn { i: i + 1, j: j + 1 });
+        }
+        if let (Some(&a), Some(&b)) = (seq.sequence.get(i), seq.sequence.get(j))
+            && a < 4 && b < 4 && !is_canonical(a, b)
+        {
+            let pair = format!("{}{}", &seq_str[i..i + 1], &seq_str[j..j + 1]);
+            issues.push(Issue::NonCanonical { i: i + 1, j: j + 1, pair });
+        }
+    }
+    issues
+}
+
+// Checks every record and, with Policy::Exclude, drops the ones with issues.
+pub fn validate(seq_list: Vec<RnaSequence>, min_hairpin: usize, policy: Policy) -> (Vec<RnaSequence>, ValidationReport) {
+    let mut report = ValidationReport { total: seq_list.len(), ..Default::default() };
+    let mut kept = Vec::with_capacity(seq_list.len());
+
+    for seq in seq_list {
+        let issues = check(&seq, min_hairpin);
+        if issues.is_empty() {
+            kept.push(seq);
+            continue;
+        }
+        report.records.push(RecordIssues { id: seq.get_id(), issues });
+        match policy {
+            Policy::Keep => kept.push(seq),
+            Policy::Exclude => report.excluded += 1,
+        }
+    }
+    (kept, report)
+}
+
+impl ValidationReport {
+    pub fn is_clean(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    // Number of records with at least one issue of the given kind.
+    pub fn count(&self, matches: fn(&Issue) -> bool) -> usize {
+        self.records
+            .iter()
+            .filter(|record| record.issues.iter().any(matches))
+            .count()
+    }
+
+    pub fn repr(&self) {
+        println!("----------- Dataset validation -----------");
+        println!("Records checked:        {}", self.total);
+        println!("Records with issues:    {}", self.records.len());
+        println!("  missing structure:    {}", self.count(|i| matches!(i, Issue::MissingStructure)));
+        println!("  unbalanced brackets:  {}", self.count(|i| matches!(i, Issue::Unbalanced(_))));
+        println!("  length mismatch:      {}", self.count(|i| matches!(i, Issue::LengthMismatch { .. })));
+        println!("  sharp turns:          {}", self.count(|i| matches!(i, Issue::SharpTurn { .. })));
+        println!("  non-canonical pairs:  {}", self.count(|i| matches!(i, Issue::NonCanonical { .. })));
+        println!("Records excluded:       {}", self.excluded);
+    }
+
+    pub fn print_records(&self) {
+        for record in &self.records {
+            let issues: Vec<String> = record.issues.iter().map(|issue| issue.to_string()).collect();
+            println!("{}: {}", record.id, issues.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issues_of_each_kind() {
+        let issues = |fold: &str, seq: &str| check(&RnaSequence::new("seq", fold, seq), 3);
+        assert!(issues("((...))", "GGAAACC").is_empty());
+        assert_eq!(issues("((.))", "GGACC"), [Issue::SharpTurn { i: 2, j: 4 }]);
+        assert_eq!(issues("(...)", "GAAAA"), [Issue::NonCanonical { i: 1, j: 5, pair: "GA".to_string() }]);
+        assert!(issues("(...)", "NAAAA").is_empty());
+        assert_eq!(issues("((...))", "GGAAACCA"), [Issue::LengthMismatch { sequence: 8, structure: 7 }]);
+        assert_eq!(issues("", "GGAAACC"), [Issue::MissingStructure]);
+        assert_eq!(Issue::NonCanonical { i: 1, j: 5, pair: "GA".to_string() }.to_string(), "non-canonical pair GA at 1-5");
+    }
+
+    #[test]
+    fn policies_keep_or_exclude() {
+        let records = || vec![RnaSequence::new("clean", "((...))", "GGAAACC"), RnaSequence::new("sharp", "((.))", "GGACC")];
+        let (kept, report) = validate(records(), 3, Policy::Keep);
+        assert_eq!((kept.len(), report.total, report.excluded, report.records.len()), (2, 2, 0, 1));
+        assert_eq!(report.records[0].id, "sharp");
+        let (kept, report) = validate(records(), 3, Policy::Exclude);
+        assert_eq!(kept.iter().map(|seq| seq.get_id()).collect::<Vec<_>>(), ["clean"]);
+        assert_eq!(report.excluded, 1);
+        assert!(!report.is_clean());
+        // with a smaller minimum loop the sharp turn is fine
+        assert!(validate(records(), 1, Policy::Exclude).1.is_clean());
+    }
+
+    #[test]
+    fn unbalanced_records_are_kept_for_the_policy() {
+        let unbalanced = RnaSequence::new("unbalanced", "((...)", "GGAAAC");
+        assert!(unbalanced.invalid_fold.is_some());
+        assert!(matches!(check(&unbalanced, 3).as_slice(), [Issue::Unbalanced(_)]));
+
+        let (kept, report) = validate(vec![unbalanced.clone()], 3, Policy::Keep);
+        assert_eq!((kept.len(), report.excluded, report.count(|i| matches!(i, Issue::Unbalanced(_)))), (1, 0, 1));
+        let (kept, report) = validate(vec![unbalanced], 3, Policy::Exclude);
+        assert_eq!((kept.len(), report.excluded), (0, 1));
+    }
+}
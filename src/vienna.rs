@@ -0,0 +1,36 @@
+use crate::folder::Folder;
+use crate::io::RnaSequence;
+use crate::structure::SecondaryStructure;
+
+use std::os::raw::c_char;
+use std::os::raw::c_void;
+use std::ffi::{CString, CStr};
+
+unsafe extern "C" {
+    fn vienna_fold(seq: *const c_char) -> *const c_char;
+    fn free_vienna_fold_result(ptr: *mut c_void);
+}
+
+// Minimum free energy structure from ViennaRNA (RNAfold defaults).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ViennaRna;
+
+impl Folder for ViennaRna {
+    fn name(&self) -> &str {
+        "vienna"
+    }
+
+    fn fold(&self, seq: &RnaSequence) -> Result<SecondaryStructure, Box<dyn std::error::Error>> {
+        let c_string = CString::new(seq.to_string())?;
+        let fold = unsafe {
+            let result_ptr = vienna_fold(c_string.as_ptr());
+            if result_ptr.is_null() {
+                return Err(format!("ViennaRNA returned no structure for {}", seq.get_id()).into());
+            }
+            let result_str = CStr::from_ptr(result_ptr).to_str().map(String::from);
+            free_vienna_fold_result(result_ptr as *mut c_void);
+            result_str?
+        };
+        SecondaryStructure::from_dot_bracket(&fold)
+    }
+}